@@ -0,0 +1,565 @@
+//! Benchmarks for the Frostgate verifier pallet
+
+use super::*;
+use crate::{
+    keys::{program_claim_commitment, BenchmarkHelper, KeyOwnershipProof, KeyVersionInfo, ProgramCacheEntry, ProgramUsage},
+    upload::{UploadId, UploadTarget},
+    verification::ProgramHasher,
+};
+use frame_benchmarking::v2::*;
+use frame_support::traits::{fungible::Mutate, EnsureOrigin, Get};
+use frame_system::RawOrigin;
+use sp_runtime::traits::{Bounded, Zero};
+use sp_std::prelude::*;
+
+const FROM_CHAIN: ChainId = 1;
+const TO_CHAIN: ChainId = 2;
+const PROGRAM: [u8; 32] = [7; 32];
+/// Upper bound of upload chunk counts benchmarked, capped by the runtime's largest upload
+const MAX_UPLOAD_CHUNKS: u32 = 1_024;
+
+fn register_chains<T: Config>() {
+    for chain_id in [FROM_CHAIN, TO_CHAIN] {
+        Chains::<T>::insert(chain_id, ChainInfo {
+            name: Default::default(),
+            enabled: true,
+            max_payload_size: T::MaxPayloadSize::get(),
+        });
+    }
+}
+
+/// Route allowing the largest set of programs
+fn full_route<T: Config>() -> RouteConfigOf<T> {
+    let mut allowed_programs = vec![PROGRAM];
+    for i in 1..T::MaxAllowedPrograms::get() {
+        let mut program = [0u8; 32];
+        program[..4].copy_from_slice(&i.to_le_bytes());
+        allowed_programs.push(program);
+    }
+    RouteConfig {
+        deposit: T::MessageDeposit::get(),
+        max_payload_size: T::MaxPayloadSize::get(),
+        allowed_programs: allowed_programs.try_into().expect("sized by MaxAllowedPrograms"),
+        verification_mode: VerificationMode::Sp1,
+        key_policy: KeyPolicy::AllowPermissionless,
+    }
+}
+
+fn funded_account<T: Config>(name: &'static str) -> T::AccountId {
+    let who: T::AccountId = account(name, 0, 0);
+    T::Currency::set_balance(&who, BalanceOf::<T>::max_value() / 2u32.into());
+    who
+}
+
+/// Add the helper key through `EmergencyKeyOrigin` as a new version, returning the program hash
+fn governance_key<T: Config>() -> Result<[u8; 32], BenchmarkError> {
+    let origin = T::EmergencyKeyOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+    let (program, key) = T::BenchmarkHelper::program_and_key();
+    let program_hash = T::ProgramHasher::program_hash(&program);
+    Pallet::<T>::add_verification_key(origin, program_hash, key, None)?;
+    Ok(program_hash)
+}
+
+/// Largest pending message with `proof` for a program with two accepted keys
+///
+/// The largest program is cached directly under the helper's program hash.
+/// Returns the sender, the message hash and the program hash.
+fn pending_message<T: Config>(
+    proof: Option<Vec<u8>>,
+) -> Result<(T::AccountId, MessageId, [u8; 32]), BenchmarkError> {
+    register_chains::<T>();
+    // The second key leaves the first in its grace period
+    governance_key::<T>()?;
+    let program_hash = governance_key::<T>()?;
+    let mut route = full_route::<T>();
+    route.allowed_programs[0] = program_hash;
+    Routes::<T>::insert(FROM_CHAIN, TO_CHAIN, route);
+
+    let program: BoundedVec<u8, T::MaxProgramSize> =
+        vec![1u8; T::MaxProgramSize::get() as usize].try_into().expect("sized by MaxProgramSize");
+    ProgramCacheUsage::<T>::insert(program_hash, ProgramUsage::new(program.len() as u32, 0));
+    ProgramCache::<T>::insert(program_hash, ProgramCacheEntry::new(program_hash, program, 0));
+
+    let caller = funded_account::<T>("caller");
+    Pallet::<T>::submit_message(
+        RawOrigin::Signed(caller.clone()).into(),
+        FROM_CHAIN,
+        TO_CHAIN,
+        0,
+        0,
+        [1; 32],
+        program_hash,
+        vec![0u8; T::MaxPayloadSize::get() as usize],
+        proof,
+        Some(1u32.into()),
+    )?;
+    let hash = Messages::<T>::iter_keys().next().expect("message stored");
+    Ok((caller, hash, program_hash))
+}
+
+/// Schedule an owner-registered key, returning the owner and program hash
+fn registered_key<T: Config>() -> (T::AccountId, [u8; 32]) {
+    let caller = funded_account::<T>("caller");
+    let (program, key) = T::BenchmarkHelper::program_and_key();
+    let program_hash = T::ProgramHasher::program_hash(&program);
+    frame_system::Pallet::<T>::set_block_number(2u32.into());
+    ProgramClaims::<T>::insert(&caller, (program_claim_commitment(&caller, &program), 1, Zero::zero()));
+    Pallet::<T>::register_verification_key(
+        RawOrigin::Signed(caller.clone()).into(),
+        program_hash,
+        key,
+        None,
+        KeyOwnershipProof::ProgramBytes(program),
+    )
+    .expect("key registered");
+    (caller, program_hash)
+}
+
+/// Upload of a program of `chunks` full chunks, at most the largest program
+///
+/// Returns the upload ID and the program bytes; a key is stored for the
+/// program so that the upload can begin.
+fn program_upload<T: Config>(owner: &T::AccountId, chunks: u32, append: bool) -> (UploadId, Vec<u8>) {
+    let length = chunks.saturating_mul(T::MaxUploadChunkSize::get()).min(T::MaxProgramSize::get());
+    let program = vec![1u8; length as usize];
+    let program_hash = T::ProgramHasher::program_hash(&program);
+    ActiveKeyVersions::<T>::insert(program_hash, KeyVersionInfo::initial());
+
+    let upload_id = NextUploadId::<T>::get();
+    Pallet::<T>::begin_upload(RawOrigin::Signed(owner.clone()).into(), UploadTarget::Program, program_hash, length)
+        .expect("upload begun");
+    if append {
+        for chunk in program.chunks(T::MaxUploadChunkSize::get() as usize) {
+            Pallet::<T>::append_chunk(
+                RawOrigin::Signed(owner.clone()).into(),
+                upload_id,
+                chunk.to_vec().try_into().expect("chunk within MaxUploadChunkSize"),
+            )
+            .expect("chunk appended");
+        }
+    }
+    (upload_id, program)
+}
+
+#[benchmarks]
+mod benchmarks {
+    use super::*;
+
+    #[benchmark]
+    fn submit_message() {
+        register_chains::<T>();
+        Routes::<T>::insert(FROM_CHAIN, TO_CHAIN, full_route::<T>());
+        let caller = funded_account::<T>("caller");
+        let payload = vec![0u8; T::MaxPayloadSize::get() as usize];
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller), FROM_CHAIN, TO_CHAIN, 0, 0, [1; 32], PROGRAM, payload, None, Some(1u32.into()));
+
+        assert_eq!(Messages::<T>::iter().count(), 1);
+    }
+
+    /// A largest proof tried against the active key and one in its grace
+    /// period, with the largest program loaded
+    #[benchmark]
+    fn verify_message() -> Result<(), BenchmarkError> {
+        let (_, hash, program_hash) =
+            pending_message::<T>(Some(vec![0u8; T::MaxProofSize::get() as usize]))?;
+        let relayer = funded_account::<T>("relayer");
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(relayer), hash);
+
+        assert!(ProgramCacheUsage::<T>::get(program_hash).is_some());
+        Ok(())
+    }
+
+    /// The sender's proof of `p` bytes, verified as in `verify_message`
+    #[benchmark]
+    fn attach_proof(p: Linear<1, { T::MaxProofSize::get() }>) -> Result<(), BenchmarkError> {
+        let (caller, hash, _) = pending_message::<T>(None)?;
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller), hash, vec![0u8; p as usize]);
+
+        assert_eq!(ProofAttempts::<T>::get(hash), 1);
+        Ok(())
+    }
+
+    /// A pending message with an escrowed reward
+    #[benchmark]
+    fn cancel_message() -> Result<(), BenchmarkError> {
+        let (caller, hash, _) = pending_message::<T>(None)?;
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller), hash);
+
+        assert!(!Messages::<T>::contains_key(hash));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn force_cancel_message() -> Result<(), BenchmarkError> {
+        let (_, hash, _) = pending_message::<T>(None)?;
+        let origin = T::ForceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, hash);
+
+        assert!(!Messages::<T>::contains_key(hash));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn register_chain() -> Result<(), BenchmarkError> {
+        let origin = T::ChainAdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let name = vec![0u8; T::MaxChainNameLen::get() as usize];
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, FROM_CHAIN, name, T::MaxPayloadSize::get());
+
+        assert!(Chains::<T>::contains_key(FROM_CHAIN));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn update_chain() -> Result<(), BenchmarkError> {
+        register_chains::<T>();
+        let origin = T::ChainAdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, FROM_CHAIN, Some(false), Some(T::MaxPayloadSize::get()));
+
+        assert!(Chains::<T>::get(FROM_CHAIN).map_or(false, |info| !info.enabled));
+        Ok(())
+    }
+
+    /// A chain passing every in-use check
+    #[benchmark]
+    fn deregister_chain() -> Result<(), BenchmarkError> {
+        register_chains::<T>();
+        let origin = T::ChainAdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, FROM_CHAIN);
+
+        assert!(!Chains::<T>::contains_key(FROM_CHAIN));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn pause() -> Result<(), BenchmarkError> {
+        register_chains::<T>();
+        Routes::<T>::insert(FROM_CHAIN, TO_CHAIN, full_route::<T>());
+        let origin = T::PauseOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let scope = PauseScope::Route(FROM_CHAIN, TO_CHAIN);
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, scope, vec![0u8; T::MaxReasonLen::get() as usize]);
+
+        assert!(Paused::<T>::contains_key(scope));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn unpause() -> Result<(), BenchmarkError> {
+        let scope = PauseScope::Route(FROM_CHAIN, TO_CHAIN);
+        Paused::<T>::insert(scope, PauseRecord {
+            paused_by: None,
+            reason: Default::default(),
+            paused_at: 0,
+        });
+        let origin = T::PauseOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, scope);
+
+        assert!(!Paused::<T>::contains_key(scope));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn set_route() -> Result<(), BenchmarkError> {
+        register_chains::<T>();
+        let origin = T::RouteAdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let route = full_route::<T>();
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, FROM_CHAIN, TO_CHAIN, route);
+
+        assert!(Routes::<T>::contains_key(FROM_CHAIN, TO_CHAIN));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn remove_route() -> Result<(), BenchmarkError> {
+        register_chains::<T>();
+        Routes::<T>::insert(FROM_CHAIN, TO_CHAIN, full_route::<T>());
+        ChainRouteCount::<T>::insert(FROM_CHAIN, 1);
+        ChainRouteCount::<T>::insert(TO_CHAIN, 1);
+        Paused::<T>::insert(PauseScope::Route(FROM_CHAIN, TO_CHAIN), PauseRecord {
+            paused_by: None,
+            reason: Default::default(),
+            paused_at: 0,
+        });
+        let origin = T::RouteAdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, FROM_CHAIN, TO_CHAIN);
+
+        assert!(!Routes::<T>::contains_key(FROM_CHAIN, TO_CHAIN));
+        assert_eq!(ChainRouteCount::<T>::get(FROM_CHAIN), 0);
+        Ok(())
+    }
+
+    #[benchmark]
+    fn set_volume_limit() -> Result<(), BenchmarkError> {
+        register_chains::<T>();
+        let origin = T::RouteAdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, FROM_CHAIN, Some(u128::MAX));
+
+        assert_eq!(ChainVolumeLimits::<T>::get(FROM_CHAIN), Some(u128::MAX));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn register_verification_key() {
+        let caller = funded_account::<T>("caller");
+        let (program, key) = T::BenchmarkHelper::program_and_key();
+        let program_hash = T::ProgramHasher::program_hash(&program);
+        // The claim must be committed in an earlier block
+        frame_system::Pallet::<T>::set_block_number(2u32.into());
+        ProgramClaims::<T>::insert(&caller, (program_claim_commitment(&caller, &program), 1, Zero::zero()));
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()), program_hash, key, None, KeyOwnershipProof::ProgramBytes(program));
+
+        assert_eq!(ProgramOwners::<T>::get(program_hash), Some(caller));
+    }
+
+    #[benchmark]
+    fn add_verification_key() -> Result<(), BenchmarkError> {
+        let origin = T::EmergencyKeyOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let (program, key) = T::BenchmarkHelper::program_and_key();
+        let program_hash = T::ProgramHasher::program_hash(&program);
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, program_hash, key, None);
+
+        assert!(ActiveKeyVersions::<T>::contains_key(program_hash));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn schedule_verification_key() -> Result<(), BenchmarkError> {
+        let origin = T::KeyAdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let (program, key) = T::BenchmarkHelper::program_and_key();
+        let program_hash = T::ProgramHasher::program_hash(&program);
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, program_hash, key, None);
+
+        assert!(ScheduledKeys::<T>::contains_key(program_hash));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn cancel_scheduled_key() {
+        let (caller, program_hash) = registered_key::<T>();
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller), program_hash);
+
+        assert!(!ScheduledKeys::<T>::contains_key(program_hash));
+        assert!(!ScheduledKeyDeposits::<T>::contains_key(program_hash));
+    }
+
+    #[benchmark]
+    fn activate_scheduled_key() {
+        let (caller, program_hash) = registered_key::<T>();
+        let due = frame_system::Pallet::<T>::block_number() + T::KeyActivationDelay::get().into();
+        frame_system::Pallet::<T>::set_block_number(due);
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller), program_hash);
+
+        assert!(ActiveKeyVersions::<T>::contains_key(program_hash));
+        assert!(KeyDeposits::<T>::contains_key(program_hash, 1));
+    }
+
+    #[benchmark]
+    fn remove_verification_key() {
+        let (caller, program_hash) = registered_key::<T>();
+        let due = frame_system::Pallet::<T>::block_number() + T::KeyActivationDelay::get().into();
+        frame_system::Pallet::<T>::set_block_number(due);
+        Pallet::<T>::activate_scheduled_key(RawOrigin::Signed(caller.clone()).into(), program_hash)
+            .expect("key activated");
+        // Rotate the key out without revoking it, so that the accepted versions are read
+        ActiveKeyVersions::<T>::mutate(program_hash, |info| {
+            if let Some(info) = info {
+                info.active = 2;
+                info.previous = None;
+            }
+        });
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller), program_hash, 1);
+
+        assert!(!KeyDeposits::<T>::contains_key(program_hash, 1));
+        assert!(!VerificationKeys::<T>::contains_key(program_hash, 1));
+    }
+
+    #[benchmark]
+    fn cache_program() -> Result<(), BenchmarkError> {
+        let origin = T::ProgramAdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let program = vec![1u8; T::MaxProgramSize::get() as usize];
+        let program_hash = T::ProgramHasher::program_hash(&program);
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, program_hash, program);
+
+        assert!(ProgramCache::<T>::contains_key(program_hash));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn begin_upload() {
+        let caller = funded_account::<T>("caller");
+        let program_hash = T::ProgramHasher::program_hash(&[1]);
+        ActiveKeyVersions::<T>::insert(program_hash, KeyVersionInfo::initial());
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller), UploadTarget::Program, program_hash, T::MaxProgramSize::get());
+
+        assert!(Uploads::<T>::contains_key(0));
+    }
+
+    #[benchmark]
+    fn append_chunk() {
+        let caller = funded_account::<T>("caller");
+        let (upload_id, program) = program_upload::<T>(&caller, 1, false);
+        let chunk = program[..T::MaxUploadChunkSize::get().min(program.len() as u32) as usize].to_vec();
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller), upload_id, chunk.try_into().expect("chunk within MaxUploadChunkSize"));
+
+        assert_eq!(Uploads::<T>::get(upload_id).map(|upload| upload.chunks), Some(1));
+    }
+
+    #[benchmark]
+    fn finalize_upload(c: Linear<1, MAX_UPLOAD_CHUNKS>) {
+        let caller = funded_account::<T>("caller");
+        let (upload_id, program) = program_upload::<T>(&caller, c, true);
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller), upload_id);
+
+        assert!(ProgramDeposits::<T>::contains_key(T::ProgramHasher::program_hash(&program)));
+    }
+
+    #[benchmark]
+    fn cancel_upload(c: Linear<1, MAX_UPLOAD_CHUNKS>) {
+        let caller = funded_account::<T>("caller");
+        let (upload_id, _) = program_upload::<T>(&caller, c, true);
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller), upload_id);
+
+        assert!(!Uploads::<T>::contains_key(upload_id));
+    }
+
+    #[benchmark]
+    fn commit_program_claim() {
+        let caller: T::AccountId = whitelisted_caller();
+        T::Currency::set_balance(&caller, BalanceOf::<T>::max_value() / 2u32.into());
+        // Replacing an earlier claim also releases its deposit
+        Pallet::<T>::commit_program_claim(RawOrigin::Signed(caller.clone()).into(), [0; 32])
+            .expect("claim committed");
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()), [1; 32]);
+
+        assert!(ProgramClaims::<T>::contains_key(caller));
+    }
+
+    #[benchmark]
+    fn freeze_verification_key() -> Result<(), BenchmarkError> {
+        let program_hash = governance_key::<T>()?;
+        let origin = T::KeyAdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, program_hash, 1, vec![0u8; T::MaxReasonLen::get() as usize]);
+
+        assert!(VerificationKeys::<T>::get(program_hash, 1).map_or(false, |entry| !entry.is_active()));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn thaw_verification_key() -> Result<(), BenchmarkError> {
+        let program_hash = governance_key::<T>()?;
+        let origin = T::KeyAdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let reason = vec![0u8; T::MaxReasonLen::get() as usize];
+        Pallet::<T>::freeze_verification_key(origin.clone(), program_hash, 1, reason)?;
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, program_hash, 1);
+
+        assert!(VerificationKeys::<T>::get(program_hash, 1).map_or(false, |entry| entry.is_active()));
+        Ok(())
+    }
+
+    #[benchmark]
+    fn revoke_verification_key() -> Result<(), BenchmarkError> {
+        let program_hash = governance_key::<T>()?;
+        let origin = T::KeyAdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, program_hash, 1, vec![0u8; T::MaxReasonLen::get() as usize]);
+
+        assert!(VerificationKeys::<T>::get(program_hash, 1).map_or(false, |entry| entry.is_revoked()));
+        Ok(())
+    }
+
+    /// `n` largest pending messages of a program whose key is revoked
+    #[benchmark]
+    fn fail_messages_for_revoked_key(n: Linear<1, { T::MaxRevokedMessageBatch::get() }>) -> Result<(), BenchmarkError> {
+        let program_hash = governance_key::<T>()?;
+        let origin = T::KeyAdminOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let reason = vec![0u8; T::MaxReasonLen::get() as usize];
+        Pallet::<T>::revoke_verification_key(origin, program_hash, 1, reason)?;
+
+        let sender = funded_account::<T>("sender");
+        let mut message_ids = Vec::new();
+        for i in 0..n {
+            let id = MessageId::from_low_u64_be(i as u64);
+            Messages::<T>::insert(id, Message {
+                from_chain: FROM_CHAIN,
+                to_chain: TO_CHAIN,
+                sender: sender.clone(),
+                source_sender: [1; 32],
+                program_hash,
+                payload: vec![0u8; T::MaxPayloadSize::get() as usize].try_into().expect("sized by MaxPayloadSize"),
+                lane: 0,
+                nonce: i as u64,
+                timestamp: 0,
+                status: MessageStatus::Pending,
+                proof: Some(vec![0u8; T::MaxProofSize::get() as usize].try_into().expect("sized by MaxProofSize")),
+                key_version: None,
+                deposit: Zero::zero(),
+            });
+            message_ids.push(id);
+        }
+        let caller = funded_account::<T>("caller");
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller), program_hash, message_ids.clone().try_into().expect("at most the batch size"));
+
+        for id in message_ids {
+            assert_eq!(Messages::<T>::get(id).map(|message| message.status), Some(MessageStatus::Failed));
+        }
+        Ok(())
+    }
+
+    impl_benchmark_test_suite!(Pallet, crate::mock::new_test_ext(), crate::mock::Test);
+}
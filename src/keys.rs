@@ -1,7 +1,11 @@
-use sp_std::prelude::*;
-use codec::{Decode, Encode};
-use sp_runtime::RuntimeDebug;
+use sp_std::{fmt::Debug, prelude::*};
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{
+    traits::{ConstU32, Get}, BoundedVec, CloneNoBound, EqNoBound, PartialEqNoBound, RuntimeDebugNoBound,
+};
 use scale_info::TypeInfo;
+use sp_io::hashing::blake2_256;
+use sp_runtime::RuntimeDebug;
 
 /// Key management error types
 #[derive(Debug, Encode, Decode, PartialEq, Eq)]
@@ -12,6 +16,8 @@ pub enum KeyError {
     NotFound,
     /// Invalid program hash
     InvalidProgramHash,
+    /// Key status does not allow the transition
+    InvalidStatus,
     /// System error
     SystemError,
 }
@@ -19,33 +25,155 @@ pub enum KeyError {
 /// Result type for key operations
 pub type KeyResult<T> = Result<T, KeyError>;
 
+/// Whether a verification key may be used
+#[derive(CloneNoBound, PartialEqNoBound, EqNoBound, Encode, Decode, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen)]
+#[codec(mel_bound())]
+#[scale_info(skip_type_params(MaxReasonLen))]
+pub enum KeyStatus<MaxReasonLen: Get<u32>> {
+    /// Key verifies proofs
+    Active,
+    /// Key is temporarily disabled and can be thawed
+    Frozen {
+        /// Reason given for the freeze
+        reason: BoundedVec<u8, MaxReasonLen>,
+        /// Block number of the freeze
+        since: u64,
+    },
+    /// Key is permanently disabled
+    Revoked {
+        /// Reason given for the revocation
+        reason: BoundedVec<u8, MaxReasonLen>,
+        /// Block number of the revocation
+        since: u64,
+    },
+}
+
+/// Provenance of a verification key
+#[derive(CloneNoBound, PartialEqNoBound, EqNoBound, Encode, Decode, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen)]
+#[codec(mel_bound(AccountId: MaxEncodedLen))]
+#[scale_info(skip_type_params(MaxLen))]
+pub struct KeyMetadata<AccountId: Clone + PartialEq + Eq + Debug, MaxLen: Get<u32>> {
+    /// Name of the circuit the key verifies
+    pub circuit_name: BoundedVec<u8, MaxLen>,
+    /// Version of the circuit
+    pub circuit_version: BoundedVec<u8, MaxLen>,
+    /// Commit of the circuit source in its repository
+    pub source_commit: BoundedVec<u8, MaxLen>,
+    /// Description of the public inputs the circuit exposes
+    pub public_input_layout: BoundedVec<u8, MaxLen>,
+    /// Hash of the trusted setup transcript, `None` for transparent setups
+    pub setup_transcript_hash: Option<[u8; 32]>,
+    /// Account responsible for the circuit
+    pub owner: Option<AccountId>,
+}
+
+/// Key metadata as supplied to calls, before its fields are checked against `MaxMetadataSize`
+pub type KeyMetadataInput<AccountId> = KeyMetadata<AccountId, ConstU32<{ u32::MAX }>>;
+
+impl<AccountId: Clone + PartialEq + Eq + Debug, MaxLen: Get<u32>> KeyMetadata<AccountId, MaxLen> {
+    /// The same metadata with fields bounded by `NewMaxLen`, `None` if a field is longer
+    pub fn rebound<NewMaxLen: Get<u32>>(self) -> Option<KeyMetadata<AccountId, NewMaxLen>> {
+        Some(KeyMetadata {
+            circuit_name: self.circuit_name.into_inner().try_into().ok()?,
+            circuit_version: self.circuit_version.into_inner().try_into().ok()?,
+            source_commit: self.source_commit.into_inner().try_into().ok()?,
+            public_input_layout: self.public_input_layout.into_inner().try_into().ok()?,
+            setup_transcript_hash: self.setup_transcript_hash,
+            owner: self.owner,
+        })
+    }
+}
+
 /// Verification key entry stored on-chain
-#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
-pub struct VerificationKeyEntry {
+#[derive(CloneNoBound, PartialEqNoBound, EqNoBound, Encode, Decode, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen)]
+#[codec(mel_bound(AccountId: MaxEncodedLen))]
+#[scale_info(skip_type_params(MaxKeySize, MaxPreparedKeySize, MaxMetadataSize, MaxReasonLen))]
+pub struct VerificationKeyEntry<
+    AccountId: Clone + PartialEq + Eq + Debug,
+    MaxKeySize: Get<u32>,
+    MaxPreparedKeySize: Get<u32>,
+    MaxMetadataSize: Get<u32>,
+    MaxReasonLen: Get<u32>,
+> {
     /// Program hash this key is for
     pub program_hash: [u8; 32],
     /// Verification key bytes
-    pub key_bytes: Vec<u8>,
+    pub key_bytes: BoundedVec<u8, MaxKeySize>,
+    /// Key parsed and preprocessed by the backend, used for verification
+    pub prepared_key: BoundedVec<u8, MaxPreparedKeySize>,
     /// Block number when this key was added
     pub added_at: u64,
-    /// Optional metadata
-    pub metadata: Option<Vec<u8>>,
+    /// Signed account that added the key, `None` for non-signed origins such as root or a collective
+    pub added_by: Option<AccountId>,
+    /// Optional provenance metadata
+    pub metadata: Option<KeyMetadata<AccountId, MaxMetadataSize>>,
+    /// Whether the key may be used
+    pub status: KeyStatus<MaxReasonLen>,
 }
 
-impl VerificationKeyEntry {
-    /// Create a new verification key entry
+impl<
+        AccountId: Clone + PartialEq + Eq + Debug,
+        MaxKeySize: Get<u32>,
+        MaxPreparedKeySize: Get<u32>,
+        MaxMetadataSize: Get<u32>,
+        MaxReasonLen: Get<u32>,
+    > VerificationKeyEntry<AccountId, MaxKeySize, MaxPreparedKeySize, MaxMetadataSize, MaxReasonLen>
+{
+    /// Create a new, active verification key entry
     pub fn new(
         program_hash: [u8; 32],
-        key_bytes: Vec<u8>,
+        key_bytes: BoundedVec<u8, MaxKeySize>,
+        prepared_key: BoundedVec<u8, MaxPreparedKeySize>,
         added_at: u64,
-        metadata: Option<Vec<u8>>,
+        added_by: Option<AccountId>,
+        metadata: Option<KeyMetadata<AccountId, MaxMetadataSize>>,
     ) -> Self {
         Self {
             program_hash,
             key_bytes,
+            prepared_key,
             added_at,
+            added_by,
             metadata,
+            status: KeyStatus::Active,
+        }
+    }
+
+    /// Whether the key may verify proofs
+    pub fn is_active(&self) -> bool {
+        matches!(self.status, KeyStatus::Active)
+    }
+
+    /// Whether the key was revoked
+    pub fn is_revoked(&self) -> bool {
+        matches!(self.status, KeyStatus::Revoked { .. })
+    }
+
+    /// Temporarily disable an active key
+    pub fn freeze(&mut self, reason: BoundedVec<u8, MaxReasonLen>, now: u64) -> KeyResult<()> {
+        if !self.is_active() {
+            return Err(KeyError::InvalidStatus);
+        }
+        self.status = KeyStatus::Frozen { reason, since: now };
+        Ok(())
+    }
+
+    /// Re-enable a frozen key
+    pub fn thaw(&mut self) -> KeyResult<()> {
+        if !matches!(self.status, KeyStatus::Frozen { .. }) {
+            return Err(KeyError::InvalidStatus);
         }
+        self.status = KeyStatus::Active;
+        Ok(())
+    }
+
+    /// Permanently disable an active or frozen key
+    pub fn revoke(&mut self, reason: BoundedVec<u8, MaxReasonLen>, now: u64) -> KeyResult<()> {
+        if self.is_revoked() {
+            return Err(KeyError::InvalidStatus);
+        }
+        self.status = KeyStatus::Revoked { reason, since: now };
+        Ok(())
     }
 
     /// Validate the key entry format
@@ -64,31 +192,165 @@ impl VerificationKeyEntry {
     }
 }
 
-/// Program cache entry
+/// Domain separator of key registration payloads signed by program owners
+pub const KEY_REGISTRATION_DOMAIN: &[u8] = b"frostgate/register-key/v1";
+
+/// Domain separator of program claim commitments
+pub const PROGRAM_CLAIM_DOMAIN: &[u8] = b"frostgate/claim-program/v1";
+
+/// Proof that the caller may register keys for a program
 #[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
-pub struct ProgramCacheEntry {
+pub enum KeyOwnershipProof<Signature> {
+    /// Program bytes hashing to the program hash
+    ///
+    /// Program bytes are usually public, so this only claims a program that
+    /// has no owner and no governance keys yet. The claimant must have
+    /// committed to [`program_claim_commitment`] in an earlier block, so the
+    /// bytes cannot be copied from a pending claim and front-run.
+    ProgramBytes(Vec<u8>),
+    /// Signature by the program owner over [`key_registration_payload`]
+    Signature(Signature),
+}
+
+/// Payload a program owner signs to authorise a key registration
+///
+/// Commits to the version the key will receive and to the program's
+/// registration nonce, so that a signature authorises one registration and
+/// cannot be replayed after the next rotation or after the key is cancelled.
+pub fn key_registration_payload(
+    program_hash: &[u8; 32],
+    version: KeyVersion,
+    nonce: u32,
+    key_bytes: &[u8],
+) -> Vec<u8> {
+    (KEY_REGISTRATION_DOMAIN, program_hash, version, nonce, blake2_256(key_bytes)).encode()
+}
+
+/// Commitment an account makes before claiming a program with its bytes
+///
+/// Binds the claimant, so a commitment seen on chain cannot be reused by
+/// another account.
+pub fn program_claim_commitment<AccountId: Encode>(who: &AccountId, program_bytes: &[u8]) -> [u8; 32] {
+    blake2_256(&(PROGRAM_CLAIM_DOMAIN, who, blake2_256(program_bytes)).encode())
+}
+
+/// Supplies key material that passes the runtime's key checks in benchmarks
+#[cfg(feature = "runtime-benchmarks")]
+pub trait BenchmarkHelper {
+    /// Program bytes and a verification key for them
+    fn program_and_key() -> (Vec<u8>, Vec<u8>);
+}
+
+/// Version of a program's verification key, starting at 1
+pub type KeyVersion = u32;
+
+/// Key versions accepted for a program
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct KeyVersionInfo {
+    /// Version used for new proofs
+    pub active: KeyVersion,
+    /// Version replaced by the last rotation
+    pub previous: Option<KeyVersion>,
+    /// Last block at which `previous` is still accepted
+    pub previous_valid_until: u64,
+}
+
+impl KeyVersionInfo {
+    /// Versions of a program's first key
+    pub fn initial() -> Self {
+        Self {
+            active: 1,
+            previous: None,
+            previous_valid_until: 0,
+        }
+    }
+
+    /// Activate the next version, accepting the current one until `grace_until`
+    pub fn rotate(&self, grace_until: u64) -> Self {
+        Self {
+            active: self.active.saturating_add(1),
+            previous: Some(self.active),
+            previous_valid_until: grace_until,
+        }
+    }
+
+    /// Versions accepted at block `now`, active first
+    pub fn accepted_at(&self, now: u64) -> Vec<KeyVersion> {
+        let mut versions = vec![self.active];
+        if let Some(previous) = self.previous {
+            if now <= self.previous_valid_until {
+                versions.push(previous);
+            }
+        }
+        versions
+    }
+}
+
+/// Verification key waiting for its activation block
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct ScheduledKey<Entry> {
+    /// Key to activate
+    pub entry: Entry,
+    /// First block at which the key can be activated
+    pub activate_at: u64,
+}
+
+impl<Entry> ScheduledKey<Entry> {
+    /// Whether the key can be activated at block `now`
+    pub fn is_due(&self, now: u64) -> bool {
+        now >= self.activate_at
+    }
+}
+
+/// Program cache entry
+#[derive(CloneNoBound, PartialEqNoBound, EqNoBound, Encode, Decode, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen)]
+#[codec(mel_bound())]
+#[scale_info(skip_type_params(MaxProgramSize))]
+pub struct ProgramCacheEntry<MaxProgramSize: Get<u32>> {
     /// Program hash
     pub hash: [u8; 32],
     /// Program bytes
-    pub bytes: Vec<u8>,
+    pub bytes: BoundedVec<u8, MaxProgramSize>,
     /// Block number when cached
     pub cached_at: u64,
-    /// Number of times used
-    pub use_count: u64,
 }
 
-impl ProgramCacheEntry {
+impl<MaxProgramSize: Get<u32>> ProgramCacheEntry<MaxProgramSize> {
     /// Create a new program cache entry
     pub fn new(
         hash: [u8; 32],
-        bytes: Vec<u8>,
+        bytes: BoundedVec<u8, MaxProgramSize>,
         cached_at: u64,
     ) -> Self {
         Self {
             hash,
             bytes,
             cached_at,
+        }
+    }
+}
+
+/// Usage of a cached program
+///
+/// Kept apart from the program bytes, so that recording a use and sweeping
+/// the cache do not read or rewrite the program.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct ProgramUsage {
+    /// Size of the program in bytes
+    pub size: u32,
+    /// Number of times used
+    pub use_count: u64,
+    /// Block number of the last use, or of caching if unused
+    pub last_used: u64,
+}
+
+impl ProgramUsage {
+    /// Usage of a program of `size` bytes cached at block `cached_at`
+    pub fn new(size: u32, cached_at: u64) -> Self {
+        Self {
+            size,
             use_count: 0,
+            last_used: cached_at,
         }
     }
 
@@ -96,41 +358,158 @@ impl ProgramCacheEntry {
     pub fn increment_use_count(&mut self) {
         self.use_count = self.use_count.saturating_add(1);
     }
+
+    /// Record a use at block `now`
+    pub fn record_use(&mut self, now: u64) {
+        self.increment_use_count();
+        self.last_used = now;
+    }
+
+    /// Whether the program may be evicted at block `now`
+    ///
+    /// Programs unused for `max_age` blocks are always evictable. While the
+    /// cache is over budget, a program used `n` times is evictable after
+    /// `max_age * (n + 1) / (n + 2)` blocks, so unused programs go first but
+    /// still get half of `max_age` to be used, and frequently used ones are
+    /// kept almost as long as stale ones.
+    pub fn is_evictable(&self, now: u64, max_age: u64, over_budget: bool) -> bool {
+        let idle = now.saturating_sub(self.last_used);
+        if idle >= max_age {
+            return true;
+        }
+        if !over_budget {
+            return false;
+        }
+        let uses = self.use_count.min(u32::MAX as u64) as u128;
+        (idle as u128) * (uses + 2) >= (max_age as u128) * (uses + 1)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use frame_support::traits::ConstU32;
+
+    type TestKeyEntry = VerificationKeyEntry<u64, ConstU32<16>, ConstU32<16>, ConstU32<16>, ConstU32<16>>;
 
     #[test]
     fn test_verification_key_validation() {
-        let valid_key = VerificationKeyEntry::new(
+        let valid_key = TestKeyEntry::new(
             [1; 32],
-            vec![1, 2, 3],
+            vec![1, 2, 3].try_into().unwrap(),
+            vec![1, 2, 3].try_into().unwrap(),
             1,
+            Some(1),
             None,
         );
         assert!(valid_key.validate().is_ok());
 
-        let invalid_key = VerificationKeyEntry::new(
+        let invalid_key = TestKeyEntry::new(
             [0; 32],
-            vec![],
+            BoundedVec::default(),
+            BoundedVec::default(),
             1,
             None,
+            None,
         );
         assert!(invalid_key.validate().is_err());
     }
 
     #[test]
-    fn test_program_cache() {
-        let mut entry = ProgramCacheEntry::new(
-            [1; 32],
-            vec![1, 2, 3],
-            1,
-        );
-        assert_eq!(entry.use_count, 0);
+    fn test_key_status_transitions() {
+        let mut key = TestKeyEntry::new([1; 32], vec![1].try_into().unwrap(), vec![1].try_into().unwrap(), 1, None, None);
+        let reason = || BoundedVec::truncate_from(b"bug".to_vec());
+
+        assert_eq!(key.thaw(), Err(KeyError::InvalidStatus));
+
+        assert!(key.freeze(reason(), 2).is_ok());
+        assert!(!key.is_active());
+        assert_eq!(key.freeze(reason(), 3), Err(KeyError::InvalidStatus));
+
+        assert!(key.thaw().is_ok());
+        assert!(key.is_active());
+
+        assert!(key.revoke(reason(), 4).is_ok());
+        assert!(key.is_revoked());
+        assert_eq!(key.thaw(), Err(KeyError::InvalidStatus));
+        assert_eq!(key.revoke(reason(), 5), Err(KeyError::InvalidStatus));
+    }
+
+    #[test]
+    fn test_key_rotation_grace_period() {
+        let info = KeyVersionInfo::initial();
+        assert_eq!(info.accepted_at(1), vec![1]);
+
+        let rotated = info.rotate(10);
+        assert_eq!(rotated.active, 2);
+        assert_eq!(rotated.accepted_at(10), vec![2, 1]);
+        assert_eq!(rotated.accepted_at(11), vec![2]);
+
+        // A second rotation drops the oldest version immediately
+        let rotated = rotated.rotate(20);
+        assert_eq!(rotated.accepted_at(15), vec![3, 2]);
+    }
+
+    #[test]
+    fn test_key_registration_payload_binds_version_and_nonce() {
+        let payload = key_registration_payload(&[1; 32], 2, 0, &[1, 2, 3]);
+
+        assert_ne!(payload, key_registration_payload(&[1; 32], 3, 0, &[1, 2, 3]));
+        assert_ne!(payload, key_registration_payload(&[1; 32], 2, 1, &[1, 2, 3]));
+        assert_ne!(payload, key_registration_payload(&[1; 32], 2, 0, &[1, 2, 4]));
+        assert_eq!(payload, key_registration_payload(&[1; 32], 2, 0, &[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_program_claim_commitment_binds_claimant() {
+        let commitment = program_claim_commitment(&1u64, &[1, 2, 3]);
+
+        assert_ne!(commitment, program_claim_commitment(&2u64, &[1, 2, 3]));
+        assert_ne!(commitment, program_claim_commitment(&1u64, &[1, 2, 4]));
+    }
+
+    #[test]
+    fn test_scheduled_key_activation() {
+        let scheduled = ScheduledKey { entry: (), activate_at: 10 };
+
+        assert!(!scheduled.is_due(9));
+        assert!(scheduled.is_due(10));
+        assert!(scheduled.is_due(11));
+    }
+
+    #[test]
+    fn test_program_usage() {
+        let mut usage = ProgramUsage::new(3, 1);
+        assert_eq!(usage.use_count, 0);
+        assert_eq!(usage.last_used, 1);
+
+        usage.increment_use_count();
+        assert_eq!(usage.use_count, 1);
+
+        usage.record_use(5);
+        assert_eq!(usage.use_count, 2);
+        assert_eq!(usage.last_used, 5);
+    }
+
+    #[test]
+    fn test_program_cache_eviction_policy() {
+        let mut usage = ProgramUsage::new(1, 0);
+
+        // Stale programs go regardless of budget, fresh ones only when over budget
+        assert!(!usage.is_evictable(99, 100, false));
+        assert!(usage.is_evictable(100, 100, false));
+        // New programs are not evicted right away
+        assert!(!usage.is_evictable(0, 100, true));
+        assert!(!usage.is_evictable(49, 100, true));
+        assert!(usage.is_evictable(50, 100, true));
 
-        entry.increment_use_count();
-        assert_eq!(entry.use_count, 1);
+        // Used programs are kept longer while over budget
+        usage.record_use(0);
+        assert!(!usage.is_evictable(66, 100, true));
+        assert!(usage.is_evictable(67, 100, true));
+        usage.record_use(0);
+        usage.record_use(0);
+        assert!(!usage.is_evictable(79, 100, true));
+        assert!(usage.is_evictable(80, 100, true));
     }
 } 
\ No newline at end of file
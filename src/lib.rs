@@ -4,38 +4,157 @@ pub use pallet::*;
 
 pub mod verification;
 pub mod keys;
+pub mod message_id;
+pub mod migrations;
+pub mod rate_limit;
+pub mod runtime_api;
+pub mod sp1_keys;
+pub mod upload;
+pub mod weights;
+
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+pub use weights::WeightInfo;
+
+/// Log target used by this pallet
+pub(crate) const LOG_TARGET: &str = "runtime::frostgate-verifier";
 
 #[frame_support::pallet]
 pub mod pallet {
     use frame_support::{
         pallet_prelude::*,
-        traits::{Currency, ReservableCurrency},
-        transactional,
+        traits::{
+            fungible::{self, Mutate, MutateHold},
+            tokens::{Fortitude, Precision, Preservation, Restriction},
+            StorageVersion,
+        },
+        transactional, PalletId,
     };
     use frame_system::pallet_prelude::*;
-    use sp_std::prelude::*;
+    use sp_io::hashing::blake2_256;
+    use sp_runtime::{
+        traits::{AccountIdConversion, IdentifyAccount, Verify, Zero},
+        SaturatedConversion,
+    };
+    use sp_std::{fmt::Debug, prelude::*};
     use codec::{Decode, Encode};
     use scale_info::TypeInfo;
     use crate::{
-        verification::{VerificationContext, VerificationParams, verify_proof, VerificationError},
-        keys::{VerificationKeyEntry, ProgramCacheEntry},
+        verification::{
+            encode_public_inputs, KeyPreparer, ProgramHasher, ProofVerifier, VerificationContext, VerificationParams,
+            verify_proof, VerificationError,
+        },
+        keys::{
+            key_registration_payload, program_claim_commitment, KeyMetadata, KeyMetadataInput, KeyOwnershipProof, KeyVersion, KeyVersionInfo, ScheduledKey, VerificationKeyEntry,
+            ProgramCacheEntry, ProgramUsage,
+        },
+        message_id::{MessageHasher, MessageIdFields},
+        rate_limit::{PayloadValue, RateCounter},
+        upload::{max_chunks, Upload, UploadId, UploadTarget},
+        weights::WeightInfo,
     };
 
-    /// Chain identifier type
-    #[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
-    pub enum ChainId {
-        Ethereum = 0,
-        Polkadot = 1,
-        Solana = 2,
-        Unknown = 255,
+    pub use crate::message_id::MessageId;
+
+    /// Chain identifier type - a numeric ID registered in `Chains`
+    pub type ChainId = u32;
+
+    /// How messages from a chain are verified
+    #[derive(Clone, Copy, PartialEq, Eq, Default, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub enum VerificationMode {
+        /// SP1 proofs checked against a registered verification key
+        #[default]
+        Sp1,
     }
 
-    impl Default for ChainId {
-        fn default() -> Self {
-            ChainId::Unknown
+    /// Which verification keys may verify messages
+    #[derive(Clone, Copy, PartialEq, Eq, Default, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub enum KeyPolicy {
+        /// Only keys added by `KeyAdminOrigin`
+        #[default]
+        GovernanceOnly,
+        /// Governance keys and keys registered by program owners
+        AllowPermissionless,
+    }
+
+    /// Registry entry for a supported chain
+    #[derive(CloneNoBound, PartialEqNoBound, EqNoBound, Encode, Decode, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen)]
+    #[codec(mel_bound())]
+    #[scale_info(skip_type_params(MaxNameLen))]
+    pub struct ChainInfo<MaxNameLen: Get<u32>> {
+        /// Human readable chain name
+        pub name: BoundedVec<u8, MaxNameLen>,
+        /// Whether messages to and from this chain are accepted
+        pub enabled: bool,
+        /// Maximum payload size for messages from this chain
+        pub max_payload_size: u32,
+    }
+
+    /// Chain registry entry type as stored by a given runtime
+    pub type ChainInfoOf<T> = ChainInfo<<T as Config>::MaxChainNameLen>;
+
+    /// Configuration for messages from one chain to another
+    #[derive(CloneNoBound, PartialEqNoBound, EqNoBound, Encode, Decode, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen)]
+    #[codec(mel_bound(Balance: MaxEncodedLen))]
+    #[scale_info(skip_type_params(MaxAllowedPrograms))]
+    pub struct RouteConfig<Balance: Clone + PartialEq + Eq + Debug, MaxAllowedPrograms: Get<u32>> {
+        /// Deposit held for each message on this route
+        pub deposit: Balance,
+        /// Maximum payload size, further capped by the source chain limit
+        pub max_payload_size: u32,
+        /// Programs whose proofs are accepted on this route, empty allows none
+        pub allowed_programs: BoundedVec<[u8; 32], MaxAllowedPrograms>,
+        /// How messages on this route are verified
+        pub verification_mode: VerificationMode,
+        /// Which keys may verify messages on this route
+        pub key_policy: KeyPolicy,
+    }
+
+    impl<Balance: Clone + PartialEq + Eq + Debug, MaxAllowedPrograms: Get<u32>> RouteConfig<Balance, MaxAllowedPrograms> {
+        /// Whether proofs for `program_hash` are accepted on this route
+        pub fn allows_program(&self, program_hash: &[u8; 32]) -> bool {
+            self.allowed_programs.contains(program_hash)
         }
     }
 
+    /// Route configuration type as stored by a given runtime
+    pub type RouteConfigOf<T> = RouteConfig<BalanceOf<T>, <T as Config>::MaxAllowedPrograms>;
+
+    /// Part of the bridge affected by a pause
+    #[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub enum PauseScope {
+        /// All messages
+        Global,
+        /// Messages from or to a chain
+        Chain(ChainId),
+        /// Messages from one chain to another
+        Route(ChainId, ChainId),
+    }
+
+    /// Record of an active pause
+    #[derive(CloneNoBound, PartialEqNoBound, EqNoBound, Encode, Decode, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen)]
+    #[codec(mel_bound(AccountId: MaxEncodedLen))]
+    #[scale_info(skip_type_params(MaxReasonLen))]
+    pub struct PauseRecord<AccountId: Clone + PartialEq + Eq + Debug, MaxReasonLen: Get<u32>> {
+        /// Signed account that paused, `None` for non-signed origins such as root or a collective
+        pub paused_by: Option<AccountId>,
+        /// Reason given for the pause
+        pub reason: BoundedVec<u8, MaxReasonLen>,
+        /// Block number when the pause took effect
+        pub paused_at: u64,
+    }
+
+    /// Pause record type as stored by a given runtime
+    pub type PauseRecordOf<T> = PauseRecord<<T as frame_system::Config>::AccountId, <T as Config>::MaxReasonLen>;
+
+    /// Lane identifier - an ordered message channel from a source chain
+    pub type LaneId = u32;
+
     /// Message status
     #[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
     pub enum MessageStatus {
@@ -51,32 +170,120 @@ pub mod pallet {
     }
 
     /// Message data stored on-chain
-    #[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
-    pub struct Message<AccountId> {
-        from_chain: ChainId,
-        to_chain: ChainId,
-        sender: AccountId,
-        payload: Vec<u8>,
-        nonce: u64,
-        timestamp: u64,
-        status: MessageStatus,
-        proof: Option<Vec<u8>>,
+    #[derive(CloneNoBound, PartialEqNoBound, EqNoBound, Encode, Decode, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen)]
+    #[codec(mel_bound(AccountId: MaxEncodedLen, Balance: MaxEncodedLen))]
+    #[scale_info(skip_type_params(MaxPayloadSize, MaxProofSize))]
+    pub struct Message<
+        AccountId: Clone + PartialEq + Eq + Debug,
+        Balance: Clone + PartialEq + Eq + Debug,
+        MaxPayloadSize: Get<u32>,
+        MaxProofSize: Get<u32>,
+    > {
+        pub from_chain: ChainId,
+        pub to_chain: ChainId,
+        /// Account that submitted the message on this chain
+        pub sender: AccountId,
+        /// Sender address on the source chain
+        pub source_sender: [u8; 32],
+        /// Program whose proof attests to this message
+        pub program_hash: [u8; 32],
+        pub payload: BoundedVec<u8, MaxPayloadSize>,
+        /// Lane on the source chain the message was sent through
+        pub lane: LaneId,
+        /// Nonce assigned on the source chain, sequential per lane
+        pub nonce: u64,
+        pub timestamp: u64,
+        pub status: MessageStatus,
+        pub proof: Option<BoundedVec<u8, MaxProofSize>>,
+        /// Version of the key that verified the proof
+        pub key_version: Option<KeyVersion>,
+        /// Deposit held from `sender` for this message
+        pub deposit: Balance,
+    }
+
+    /// Message type as stored by a given runtime
+    pub type MessageOf<T> = Message<
+        <T as frame_system::Config>::AccountId,
+        BalanceOf<T>,
+        <T as Config>::MaxPayloadSize,
+        <T as Config>::MaxProofSize,
+    >;
+
+    /// Verification key entry type as stored by a given runtime
+    pub type VerificationKeyEntryOf<T> = VerificationKeyEntry<
+        <T as frame_system::Config>::AccountId,
+        <T as Config>::MaxKeySize,
+        <T as Config>::MaxPreparedKeySize,
+        <T as Config>::MaxMetadataSize,
+        <T as Config>::MaxReasonLen,
+    >;
+
+    /// Upload type as stored by a given runtime
+    pub type UploadOf<T> = Upload<<T as frame_system::Config>::AccountId, BalanceOf<T>>;
+
+    /// Scheduled key type as stored by a given runtime
+    pub type ScheduledKeyOf<T> = ScheduledKey<VerificationKeyEntryOf<T>>;
+
+    /// Key metadata type for a given runtime
+    pub type KeyMetadataOf<T> = KeyMetadata<<T as frame_system::Config>::AccountId, <T as Config>::MaxMetadataSize>;
+
+    /// Key metadata type accepted by calls of a given runtime
+    pub type KeyMetadataInputOf<T> = KeyMetadataInput<<T as frame_system::Config>::AccountId>;
+
+    /// Key ownership proof type for a given runtime
+    pub type KeyOwnershipProofOf<T> = KeyOwnershipProof<<T as Config>::OwnerSignature>;
+
+    /// Program cache entry type as stored by a given runtime
+    pub type ProgramCacheEntryOf<T> = ProgramCacheEntry<<T as Config>::MaxProgramSize>;
+
+    /// Reasons for which the pallet places funds on hold
+    #[pallet::composite_enum]
+    pub enum HoldReason {
+        /// Deposit for a submitted message
+        #[codec(index = 0)]
+        MessageDeposit,
+        /// Deposit for a registered verification key
+        #[codec(index = 1)]
+        KeyRegistration,
+        /// Deposit for a cached program
+        #[codec(index = 2)]
+        ProgramStorage,
+        /// Bond posted when challenging a message
+        #[codec(index = 3)]
+        ChallengeBond,
+        /// Reward escrowed by a submitter for the verifying relayer
+        #[codec(index = 4)]
+        RelayerReward,
+        /// Deposit for a chunked upload in progress
+        #[codec(index = 5)]
+        Upload,
+        /// Deposit for a pending program claim
+        #[codec(index = 6)]
+        ProgramClaim,
     }
 
+    /// The current storage version
+    const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
     /// Configuration trait for the pallet
     #[pallet::config]
     pub trait Config: frame_system::Config {
         /// The overarching event type
         type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
 
+        /// The overarching hold reason
+        type RuntimeHoldReason: From<HoldReason>;
+
         /// Currency type for fees and deposits
-        type Currency: ReservableCurrency<Self::AccountId>;
+        type Currency: fungible::Inspect<Self::AccountId>
+            + fungible::Mutate<Self::AccountId>
+            + fungible::MutateHold<Self::AccountId, Reason = Self::RuntimeHoldReason>;
 
         /// Maximum size of message payload
         #[pallet::constant]
         type MaxPayloadSize: Get<u32>;
 
-        /// Required deposit for submitting a message
+        /// Minimum deposit a route may require for each message
         #[pallet::constant]
         type MessageDeposit: Get<BalanceOf<Self>>;
 
@@ -84,45 +291,426 @@ pub mod pallet {
         #[pallet::constant]
         type MaxKeySize: Get<u32>;
 
-        /// Maximum age of cached programs (in blocks)
+        /// Maximum size of a prepared verification key
+        #[pallet::constant]
+        type MaxPreparedKeySize: Get<u32>;
+
+        /// Maximum size of a message proof
+        #[pallet::constant]
+        type MaxProofSize: Get<u32>;
+
+        /// Maximum length of each verification key metadata field
+        #[pallet::constant]
+        type MaxMetadataSize: Get<u32>;
+
+        /// Maximum size of a cached program
+        #[pallet::constant]
+        type MaxProgramSize: Get<u32>;
+
+        /// Blocks a cached program may go unused before it is evicted
         #[pallet::constant]
         type MaxProgramAge: Get<u32>;
+
+        /// Maximum total size of cached programs
+        ///
+        /// Caching a program that does not fit fails. While the cache is close
+        /// to full, used programs are evicted early.
+        #[pallet::constant]
+        type MaxProgramCacheBytes: Get<u64>;
+
+        /// Blocks between scheduling a key and the earliest block it can be activated
+        #[pallet::constant]
+        type KeyActivationDelay: Get<u32>;
+
+        /// Blocks for which the previous key stays accepted after a rotation
+        #[pallet::constant]
+        type KeyGracePeriod: Get<u32>;
+
+        /// Pallet ID used to derive the relayer reward fund account
+        #[pallet::constant]
+        type PalletId: Get<PalletId>;
+
+        /// Reward paid from the fund account for messages without a submitter reward
+        #[pallet::constant]
+        type RelayerReward: Get<BalanceOf<Self>>;
+
+        /// Number of blocks after submission before a message is pruned
+        #[pallet::constant]
+        type MessageTtl: Get<u32>;
+
+        /// Maximum number of proofs that can be attached to a single message
+        #[pallet::constant]
+        type MaxProofAttempts: Get<u32>;
+
+        /// Origin allowed to cancel any pending message
+        type ForceOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Origin allowed to schedule, freeze, thaw and revoke verification keys
+        type KeyAdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Origin allowed to add verification keys without the activation delay
+        ///
+        /// Meant for incident response and usually stricter than `KeyAdminOrigin`.
+        type EmergencyKeyOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Origin allowed to cache programs
+        type ProgramAdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Deposit held for a verification key registered by a program owner
+        #[pallet::constant]
+        type KeyDeposit: Get<BalanceOf<Self>>;
+
+        /// Signature by which program owners authorise key registrations
+        type OwnerSignature: Parameter + Verify<Signer = Self::OwnerSigner>;
+
+        /// Signer of `OwnerSignature`, identifying an account
+        type OwnerSigner: IdentifyAccount<AccountId = Self::AccountId>;
+
+        /// Scheme for message IDs and payload commitments, see [`crate::message_id`]
+        type MessageHasher: MessageHasher;
+
+        /// Derivation of program hashes from keys and program bytes
+        type ProgramHasher: ProgramHasher;
+
+        /// Backend parsing and preprocessing of verification keys
+        type KeyPreparer: KeyPreparer;
+
+        /// Backend verifying proofs against keys prepared by `KeyPreparer`
+        type ProofVerifier: ProofVerifier;
+
+        /// Maximum length of a registered chain name
+        #[pallet::constant]
+        type MaxChainNameLen: Get<u32>;
+
+        /// Maximum number of allowed programs per route
+        #[pallet::constant]
+        type MaxAllowedPrograms: Get<u32>;
+
+        /// Origin allowed to configure routes
+        type RouteAdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Origin allowed to register, update and deregister chains
+        type ChainAdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Origin allowed to pause and unpause the bridge
+        type PauseOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// Maximum length of a pause, freeze or revocation reason
+        #[pallet::constant]
+        type MaxReasonLen: Get<u32>;
+
+        /// Maximum number of messages failed in one call after a key revocation
+        #[pallet::constant]
+        type MaxRevokedMessageBatch: Get<u32>;
+
+        /// Maximum number of messages submitted per block
+        #[pallet::constant]
+        type MaxMessagesPerBlock: Get<u32>;
+
+        /// Window in blocks over which sender and volume counters decay
+        #[pallet::constant]
+        type RateLimitWindow: Get<u32>;
+
+        /// Maximum number of messages per sender within `RateLimitWindow`
+        #[pallet::constant]
+        type MaxMessagesPerSenderWindow: Get<u32>;
+
+        /// Extracts transferred value from payloads for volume caps
+        type PayloadValue: PayloadValue;
+
+        /// Size of each chunk of a chunked upload, except the last
+        #[pallet::constant]
+        type MaxUploadChunkSize: Get<u32>;
+
+        /// Blocks after which an unfinished upload is removed
+        #[pallet::constant]
+        type UploadTimeout: Get<u32>;
+
+        /// Deposit held per chunked upload, on top of the per-byte deposit
+        #[pallet::constant]
+        type UploadDepositBase: Get<BalanceOf<Self>>;
+
+        /// Deposit held per byte of a chunked upload until it is finalized or removed
+        #[pallet::constant]
+        type UploadDepositPerByte: Get<BalanceOf<Self>>;
+
+        /// Deposit held for a program claim commitment until it is revealed or expires
+        #[pallet::constant]
+        type ClaimDeposit: Get<BalanceOf<Self>>;
+
+        /// Blocks after which an unrevealed program claim is removed
+        #[pallet::constant]
+        type ClaimTimeout: Get<u32>;
+
+        /// Weight information for extrinsics in this pallet
+        type WeightInfo: WeightInfo;
+
+        /// Key material for benchmarks
+        #[cfg(feature = "runtime-benchmarks")]
+        type BenchmarkHelper: crate::keys::BenchmarkHelper;
     }
 
-    type BalanceOf<T> = <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+    pub type BalanceOf<T> = <<T as Config>::Currency as fungible::Inspect<<T as frame_system::Config>::AccountId>>::Balance;
 
     #[pallet::pallet]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
-    /// Message storage - maps message hash to message data
+    /// Message storage - maps canonical message ID to message data
     #[pallet::storage]
     pub type Messages<T: Config> = StorageMap<
         _,
         Blake2_128Concat,
-        T::Hash,
-        Message<T::AccountId>,
+        MessageId,
+        MessageOf<T>,
+        OptionQuery,
+    >;
+
+    /// Relayer rewards escrowed by submitters - maps message hash to reward
+    #[pallet::storage]
+    pub type MessageRewards<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        MessageId,
+        BalanceOf<T>,
         OptionQuery,
     >;
 
-    /// Nonce storage - per chain ID and account
+    /// Proof attempts made through `attach_proof` - maps message hash to count
     #[pallet::storage]
-    pub type Nonces<T: Config> = StorageDoubleMap<
+    pub type ProofAttempts<T: Config> = StorageMap<
         _,
         Blake2_128Concat,
+        MessageId,
+        u32,
+        ValueQuery,
+    >;
+
+    /// Raw storage key of the last message visited by `on_idle` pruning
+    #[pallet::storage]
+    pub type PruneCursor<T: Config> = StorageValue<_, BoundedVec<u8, ConstU32<128>>, OptionQuery>;
+
+    /// Chain registry - maps chain ID to chain info
+    #[pallet::storage]
+    pub type Chains<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        ChainId,
+        ChainInfoOf<T>,
+        OptionQuery,
+    >;
+
+    /// Route configuration - per source and destination chain
+    #[pallet::storage]
+    pub type Routes<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        ChainId,
+        Twox64Concat,
         ChainId,
+        RouteConfigOf<T>,
+        OptionQuery,
+    >;
+
+    /// Active pauses - maps scope to pause record
+    #[pallet::storage]
+    pub type Paused<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        PauseScope,
+        PauseRecordOf<T>,
+        OptionQuery,
+    >;
+
+    /// Messages submitted in the current block - (block number, count)
+    #[pallet::storage]
+    pub type BlockMessageCount<T: Config> = StorageValue<_, (u64, u32), ValueQuery>;
+
+    /// Message counters per sender
+    #[pallet::storage]
+    pub type SenderRateCounters<T: Config> = StorageMap<
+        _,
         Blake2_128Concat,
         T::AccountId,
+        RateCounter,
+        ValueQuery,
+    >;
+
+    /// Transfer volume caps per source chain within `RateLimitWindow`
+    #[pallet::storage]
+    pub type ChainVolumeLimits<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        ChainId,
+        u128,
+        OptionQuery,
+    >;
+
+    /// Number of routes from or to each chain, which keep the chain registered
+    #[pallet::storage]
+    pub type ChainRouteCount<T: Config> = StorageMap<_, Twox64Concat, ChainId, u32, ValueQuery>;
+
+    /// Transfer volume counters per source chain
+    ///
+    /// Volume is counted when a message is verified, so messages that are
+    /// cancelled, fail or expire do not use up the cap.
+    #[pallet::storage]
+    pub type ChainVolumeCounters<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        ChainId,
+        RateCounter,
+        ValueQuery,
+    >;
+
+    /// Raw key of the last sender counter checked by the `on_idle` sweep
+    #[pallet::storage]
+    pub type RateCounterCursor<T: Config> = StorageValue<_, BoundedVec<u8, ConstU32<128>>, OptionQuery>;
+
+    /// Inbound lane nonces - next source-chain nonce to verify per chain and lane
+    #[pallet::storage]
+    pub type InboundLaneNonces<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        ChainId,
+        Blake2_128Concat,
+        LaneId,
         u64,
         ValueQuery,
     >;
 
-    /// Verification key storage - maps program hash to verification key
+    /// Verification key storage - per program hash and key version
+    #[pallet::storage]
+    pub type VerificationKeys<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        [u8; 32],
+        Twox64Concat,
+        KeyVersion,
+        VerificationKeyEntryOf<T>,
+        OptionQuery,
+    >;
+
+    /// Program owners - accounts allowed to register keys without governance
+    #[pallet::storage]
+    pub type ProgramOwners<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        [u8; 32],
+        T::AccountId,
+        OptionQuery,
+    >;
+
+    /// Pending program claims - commitment, the block it was made in and the deposit held per account
+    ///
+    /// See [`crate::keys::program_claim_commitment`].
+    #[pallet::storage]
+    pub type ProgramClaims<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        ([u8; 32], u64, BalanceOf<T>),
+        OptionQuery,
+    >;
+
+    /// Raw key of the last program claim visited by the `on_idle` sweep
+    #[pallet::storage]
+    pub type ClaimCursor<T: Config> = StorageValue<_, BoundedVec<u8, ConstU32<128>>, OptionQuery>;
+
+    /// Number of owner key registrations per program, signed over by owners
+    ///
+    /// See [`crate::keys::key_registration_payload`].
+    #[pallet::storage]
+    pub type KeyRegistrationNonces<T: Config> = StorageMap<_, Blake2_128Concat, [u8; 32], u32, ValueQuery>;
+
+    /// Deposits of owner-registered keys - (depositor, amount) per program hash and key version
+    ///
+    /// Keys with a deposit are permissionless; keys without one were added by
+    /// `KeyAdminOrigin`.
+    #[pallet::storage]
+    pub type KeyDeposits<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        [u8; 32],
+        Twox64Concat,
+        KeyVersion,
+        (T::AccountId, BalanceOf<T>),
+        OptionQuery,
+    >;
+
+    /// Active and grace-period key versions per program hash
+    #[pallet::storage]
+    pub type ActiveKeyVersions<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        [u8; 32],
+        KeyVersionInfo,
+        OptionQuery,
+    >;
+
+    /// Total size in bytes of the programs in `ProgramCache`
+    #[pallet::storage]
+    pub type ProgramCacheBytes<T: Config> = StorageValue<_, u64, ValueQuery>;
+
+    /// Number of unverified messages per program, which pin the program in `ProgramCache`
+    #[pallet::storage]
+    pub type ProgramMessageCount<T: Config> = StorageMap<_, Blake2_128Concat, [u8; 32], u32, ValueQuery>;
+
+    /// Raw storage key of the last program visited by `on_idle` eviction
+    #[pallet::storage]
+    pub type EvictionCursor<T: Config> = StorageValue<_, BoundedVec<u8, ConstU32<128>>, OptionQuery>;
+
+    /// Identifier of the next chunked upload
+    #[pallet::storage]
+    pub type NextUploadId<T: Config> = StorageValue<_, UploadId, ValueQuery>;
+
+    /// Chunked uploads in progress
+    #[pallet::storage]
+    pub type Uploads<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        UploadId,
+        UploadOf<T>,
+        OptionQuery,
+    >;
+
+    /// Data of chunked uploads - per upload and chunk index
+    #[pallet::storage]
+    pub type UploadChunks<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        UploadId,
+        Twox64Concat,
+        u32,
+        BoundedVec<u8, T::MaxUploadChunkSize>,
+        OptionQuery,
+    >;
+
+    /// Raw storage key of the last upload visited by `on_idle` expiry
+    #[pallet::storage]
+    pub type UploadCursor<T: Config> = StorageValue<_, BoundedVec<u8, ConstU32<128>>, OptionQuery>;
+
+    /// Keys scheduled for activation - at most one per program hash
+    ///
+    /// Scheduled keys are public but do not verify proofs until activated.
+    #[pallet::storage]
+    pub type ScheduledKeys<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        [u8; 32],
+        ScheduledKeyOf<T>,
+        OptionQuery,
+    >;
+
+    /// Deposits of scheduled owner-registered keys - (depositor, amount) per program hash
+    ///
+    /// Moved to `KeyDeposits` when the key is activated, released if it is cancelled.
     #[pallet::storage]
-    pub type VerificationKeys<T: Config> = StorageMap<
+    pub type ScheduledKeyDeposits<T: Config> = StorageMap<
         _,
         Blake2_128Concat,
         [u8; 32],
-        VerificationKeyEntry,
+        (T::AccountId, BalanceOf<T>),
         OptionQuery,
     >;
 
@@ -132,45 +720,205 @@ pub mod pallet {
         _,
         Blake2_128Concat,
         [u8; 32],
-        ProgramCacheEntry,
+        ProgramCacheEntryOf<T>,
         OptionQuery,
     >;
 
+    /// Usage statistics of cached programs, kept apart so verification
+    /// does not rewrite the program bytes
+    #[pallet::storage]
+    pub type ProgramCacheUsage<T: Config> =
+        StorageMap<_, Blake2_128Concat, [u8; 32], ProgramUsage, OptionQuery>;
+
+    /// Account and deposit held for an uploaded program until it leaves the cache
+    #[pallet::storage]
+    pub type ProgramDeposits<T: Config> =
+        StorageMap<_, Blake2_128Concat, [u8; 32], (T::AccountId, BalanceOf<T>), OptionQuery>;
+
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
         /// A new message was submitted
         MessageSubmitted {
-            hash: T::Hash,
+            hash: MessageId,
             from_chain: ChainId,
             to_chain: ChainId,
+            lane: LaneId,
+            nonce: u64,
             sender: T::AccountId,
         },
         /// A message was verified successfully
         MessageVerified {
-            hash: T::Hash,
+            hash: MessageId,
             from_chain: ChainId,
             to_chain: ChainId,
+            key_version: KeyVersion,
+        },
+        /// A relayer was rewarded for verifying a message
+        RelayerRewarded {
+            hash: MessageId,
+            relayer: T::AccountId,
+            amount: BalanceOf<T>,
+        },
+        /// A pending or failed message was cancelled and its deposit returned
+        MessageCancelled {
+            hash: MessageId,
+            deposit_released: BalanceOf<T>,
+        },
+        /// A relayer attached a proof to a message
+        ProofAttached {
+            hash: MessageId,
+            relayer: T::AccountId,
+            attempt: u32,
+        },
+        /// A message outlived `MessageTtl` and was removed
+        ///
+        /// A `Pending` status means the message expired without being verified.
+        MessagePruned {
+            hash: MessageId,
+            status: MessageStatus,
+            deposit_released: BalanceOf<T>,
         },
         /// Message verification failed
         MessageVerificationFailed {
-            hash: T::Hash,
+            hash: MessageId,
             error: Vec<u8>,
         },
-        /// New verification key added
+        /// A chain was added to the registry
+        ChainRegistered {
+            chain_id: ChainId,
+        },
+        /// A registered chain was updated
+        ChainUpdated {
+            chain_id: ChainId,
+        },
+        /// A chain was removed from the registry
+        ChainDeregistered {
+            chain_id: ChainId,
+        },
+        /// A route was configured
+        RouteConfigured {
+            from_chain: ChainId,
+            to_chain: ChainId,
+        },
+        /// A route configuration was removed
+        RouteRemoved {
+            from_chain: ChainId,
+            to_chain: ChainId,
+        },
+        /// Part of the bridge was paused
+        BridgePaused {
+            scope: PauseScope,
+            paused_by: Option<T::AccountId>,
+            reason: Vec<u8>,
+        },
+        /// A pause was lifted
+        BridgeUnpaused {
+            scope: PauseScope,
+            unpaused_by: Option<T::AccountId>,
+        },
+        /// The transfer volume cap of a chain was changed
+        VolumeLimitSet {
+            chain_id: ChainId,
+            limit: Option<u128>,
+        },
+        /// New verification key version added and activated
         VerificationKeyAdded {
             program_hash: [u8; 32],
+            version: KeyVersion,
+            /// Previous version and the last block at which it is accepted
+            previous: Option<(KeyVersion, u64)>,
         },
         /// Program cached
         ProgramCached {
             program_hash: [u8; 32],
         },
-    }
-
-    #[pallet::error]
-    pub enum Error<T> {
-        /// Message payload too large
-        PayloadTooLarge,
+        /// A cached program was evicted
+        ProgramEvicted {
+            program_hash: [u8; 32],
+            use_count: u64,
+        },
+        /// A chunked upload was begun
+        UploadStarted {
+            upload_id: UploadId,
+            owner: T::AccountId,
+            target: UploadTarget,
+            length: u32,
+        },
+        /// A chunk was appended to an upload
+        UploadChunkAppended {
+            upload_id: UploadId,
+            received: u32,
+        },
+        /// An upload was finalized and its data moved to its target
+        UploadFinalized {
+            upload_id: UploadId,
+            target: UploadTarget,
+        },
+        /// An unfinished upload was cancelled or timed out and removed
+        UploadRemoved {
+            upload_id: UploadId,
+            expired: bool,
+            deposit_released: BalanceOf<T>,
+        },
+        /// A verification key was scheduled for activation
+        VerificationKeyScheduled {
+            program_hash: [u8; 32],
+            activate_at: u64,
+        },
+        /// A scheduled verification key was cancelled before activation
+        ScheduledKeyCancelled {
+            program_hash: [u8; 32],
+        },
+        /// A program owner was recorded
+        ProgramOwnerSet {
+            program_hash: [u8; 32],
+            owner: T::AccountId,
+        },
+        /// An owner-registered key was removed and its deposit returned
+        VerificationKeyRemoved {
+            program_hash: [u8; 32],
+            version: KeyVersion,
+            deposit_released: BalanceOf<T>,
+        },
+        /// A verification key was frozen
+        VerificationKeyFrozen {
+            program_hash: [u8; 32],
+            version: KeyVersion,
+            reason: Vec<u8>,
+        },
+        /// A frozen verification key was re-enabled
+        VerificationKeyThawed {
+            program_hash: [u8; 32],
+            version: KeyVersion,
+        },
+        /// A verification key was permanently revoked
+        VerificationKeyRevoked {
+            program_hash: [u8; 32],
+            version: KeyVersion,
+            reason: Vec<u8>,
+        },
+        /// An account committed to claiming a program with its bytes
+        ProgramClaimCommitted {
+            who: T::AccountId,
+        },
+        /// A program claim was not revealed within `ClaimTimeout` and was removed
+        ProgramClaimExpired {
+            who: T::AccountId,
+            deposit_released: BalanceOf<T>,
+        },
+        /// The storage deposit of an evicted program was released
+        ProgramDepositReleased {
+            program_hash: [u8; 32],
+            who: T::AccountId,
+            deposit_released: BalanceOf<T>,
+        },
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        /// Message payload too large
+        PayloadTooLarge,
         /// Message not found
         MessageNotFound,
         /// Invalid chain ID
@@ -189,51 +937,277 @@ pub mod pallet {
         InvalidKey,
         /// Program not found
         ProgramNotFound,
-        /// SP1 verification error
-        Sp1Error(Vec<u8>),
+        /// Message outlived its time-to-live
+        MessageExpired,
+        /// Message reached `MaxProofAttempts`
+        TooManyProofAttempts,
+        /// Caller is not the sender of the message
+        NotMessageSender,
+        /// Nonce was already delivered on this lane
+        NonceAlreadyUsed,
+        /// Earlier messages on this lane have not been verified yet
+        MessageOutOfOrder,
+        /// A message with the same ID already exists
+        DuplicateMessage,
+        /// Proof too large
+        ProofTooLarge,
+        /// A key metadata field is longer than `MaxMetadataSize`
+        MetadataTooLarge,
+        /// Program too large
+        ProgramTooLarge,
+        /// Chain is not in the registry
+        ChainNotRegistered,
+        /// Chain is already in the registry
+        ChainAlreadyRegistered,
+        /// Chain is registered but disabled
+        ChainDisabled,
+        /// Chain name too long
+        ChainNameTooLong,
+        /// Program is not allowed on this route
+        ProgramNotAllowed,
+        /// Route is not configured
+        RouteNotFound,
+        /// Messages on this route are paused
+        Paused,
+        /// Scope is not paused
+        NotPaused,
+        /// Pause reason too long
+        PauseReasonTooLong,
+        /// A message, sender or volume rate limit was reached
+        RateLimited,
+        /// Verification key version not found
+        KeyNotFound,
+        /// Key status does not allow the transition
+        InvalidKeyStatusTransition,
+        /// No accepted key of the program is active
+        KeyNotActive,
+        /// Accepted keys of the program are not all revoked
+        KeyNotRevoked,
+        /// Freeze or revocation reason too long
+        ReasonTooLong,
+        /// No active key of the program is permitted by the route's key policy
+        KeyNotPermitted,
+        /// Caller or signer is not the owner of the program
+        NotProgramOwner,
+        /// Program bytes or signature do not prove ownership
+        InvalidOwnershipProof,
+        /// Key was not registered with a deposit
+        KeyDepositNotFound,
+        /// Key is still accepted and not revoked
+        KeyStillInUse,
+        /// Program hash is not derived from the key or program bytes
+        ProgramHashMismatch,
+        /// A key is already scheduled for this program
+        KeyAlreadyScheduled,
+        /// No key is scheduled for this program
+        NoScheduledKey,
+        /// Scheduled key cannot be activated yet
+        ActivationNotDue,
+        /// Upload not found
+        UploadNotFound,
+        /// Caller did not begin the upload
+        NotUploadOwner,
+        /// Chunk is empty, not full-sized or exceeds the upload length
+        InvalidChunk,
+        /// Not all data of the upload has been received
+        UploadIncomplete,
+        /// Uploaded data does not match the upload hash
+        UploadHashMismatch,
+        /// Programs can only be uploaded for programs with a key
+        NoKeyForProgram,
+        /// Maximum payload size exceeds `MaxPayloadSize`
+        InvalidMaxPayloadSize,
+        /// Route deposit is below `MessageDeposit`
+        DepositTooLow,
+        /// No claim commitment from an earlier block matches the program bytes
+        ClaimNotCommitted,
+        /// Owners cannot rotate out keys added by governance
+        GovernanceKeyActive,
+        /// Program does not fit within `MaxProgramCacheBytes`
+        ProgramCacheFull,
+        /// Upload timed out and awaits removal
+        UploadExpired,
+        /// Chain still has routes, a pause, a volume cap or lane nonces
+        ChainInUse,
+        /// Program is already cached
+        ProgramAlreadyCached,
+    }
+
+    /// Initial bridge configuration, e.g. for development networks
+    #[pallet::genesis_config]
+    #[derive(frame_support::DefaultNoBound)]
+    pub struct GenesisConfig<T: Config> {
+        /// Enabled SP1 chains - (chain ID, name, maximum payload size)
+        pub chains: Vec<(ChainId, Vec<u8>, u32)>,
+        /// Verification keys, each active as version 1 under the program hash derived from the key
+        pub verification_keys: Vec<Vec<u8>>,
+        /// Programs to cache, stored under their derived program hash
+        ///
+        /// Each program must hash to the program hash of a genesis key.
+        pub programs: Vec<Vec<u8>>,
+        /// Routes with the default deposit and key policy -
+        /// (source chain, destination chain, maximum payload size, allowed programs)
+        pub routes: Vec<(ChainId, ChainId, u32, Vec<[u8; 32]>)>,
+        /// Next expected source-chain nonces - (chain ID, lane, nonce)
+        pub inbound_lane_nonces: Vec<(ChainId, LaneId, u64)>,
+        #[serde(skip)]
+        pub _config: sp_std::marker::PhantomData<T>,
+    }
+
+    #[pallet::genesis_build]
+    impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+        fn build(&self) {
+            for (chain_id, name, max_payload_size) in &self.chains {
+                assert!(!Chains::<T>::contains_key(chain_id), "duplicate genesis chain");
+                assert!(
+                    *max_payload_size <= T::MaxPayloadSize::get(),
+                    "genesis chain max payload size exceeds MaxPayloadSize"
+                );
+                Chains::<T>::insert(chain_id, ChainInfo {
+                    name: name.clone().try_into().expect("genesis chain name too long"),
+                    enabled: true,
+                    max_payload_size: *max_payload_size,
+                });
+            }
+
+            for (from_chain, to_chain, max_payload_size, allowed_programs) in &self.routes {
+                assert!(
+                    Chains::<T>::contains_key(from_chain) && Chains::<T>::contains_key(to_chain),
+                    "genesis route between unregistered chains"
+                );
+                assert!(!Routes::<T>::contains_key(from_chain, to_chain), "duplicate genesis route");
+                assert!(
+                    *max_payload_size <= T::MaxPayloadSize::get(),
+                    "genesis route max payload size exceeds MaxPayloadSize"
+                );
+                Pallet::<T>::count_route(from_chain, to_chain);
+                Routes::<T>::insert(from_chain, to_chain, RouteConfig {
+                    deposit: T::MessageDeposit::get(),
+                    max_payload_size: *max_payload_size,
+                    allowed_programs: allowed_programs.clone().try_into().expect("too many genesis route programs"),
+                    verification_mode: VerificationMode::Sp1,
+                    key_policy: KeyPolicy::GovernanceOnly,
+                });
+            }
+
+            for key_bytes in &self.verification_keys {
+                let program_hash = T::ProgramHasher::key_program_hash(key_bytes)
+                    .expect("genesis verification key does not commit to a program");
+                assert!(!ActiveKeyVersions::<T>::contains_key(program_hash), "duplicate genesis key");
+                let key_entry = Pallet::<T>::new_key_entry(program_hash, key_bytes.clone(), None, None)
+                    .expect("invalid genesis verification key");
+                let versions = KeyVersionInfo::initial();
+                VerificationKeys::<T>::insert(program_hash, versions.active, key_entry);
+                ActiveKeyVersions::<T>::insert(program_hash, versions);
+            }
+
+            for program_bytes in &self.programs {
+                let program_hash = T::ProgramHasher::program_hash(program_bytes);
+                assert!(
+                    ActiveKeyVersions::<T>::contains_key(program_hash),
+                    "genesis program has no genesis verification key"
+                );
+                let bytes = program_bytes.clone().try_into().expect("genesis program too large");
+                Pallet::<T>::insert_program(ProgramCacheEntry::new(program_hash, bytes, 0))
+                    .expect("genesis programs exceed MaxProgramCacheBytes");
+            }
+
+            for (chain_id, lane, nonce) in &self.inbound_lane_nonces {
+                InboundLaneNonces::<T>::insert(chain_id, lane, nonce);
+            }
+        }
+    }
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_idle(_n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+            let mut used = Self::prune_messages(remaining_weight);
+            used = used.saturating_add(Self::evict_programs(remaining_weight.saturating_sub(used)));
+            used = used.saturating_add(Self::expire_uploads(remaining_weight.saturating_sub(used)));
+            used = used.saturating_add(Self::expire_claims(remaining_weight.saturating_sub(used)));
+            used.saturating_add(Self::prune_rate_counters(remaining_weight.saturating_sub(used)))
+        }
     }
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
         /// Submit a new message for verification
         #[pallet::call_index(0)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(T::WeightInfo::submit_message())]
         #[transactional]
         pub fn submit_message(
             origin: OriginFor<T>,
             from_chain: ChainId,
             to_chain: ChainId,
+            lane: LaneId,
+            nonce: u64,
+            source_sender: [u8; 32],
+            program_hash: [u8; 32],
             payload: Vec<u8>,
             proof: Option<Vec<u8>>,
+            reward: Option<BalanceOf<T>>,
         ) -> DispatchResult {
             let sender = ensure_signed(origin)?;
 
             // Validate inputs
-            ensure!(payload.len() <= T::MaxPayloadSize::get() as usize, Error::<T>::PayloadTooLarge);
-            ensure!(from_chain != ChainId::Unknown && to_chain != ChainId::Unknown, Error::<T>::InvalidChainId);
+            let payload: BoundedVec<u8, T::MaxPayloadSize> =
+                payload.try_into().map_err(|_| Error::<T>::PayloadTooLarge)?;
+            let proof = proof
+                .map(|p| BoundedVec::<u8, T::MaxProofSize>::try_from(p))
+                .transpose()
+                .map_err(|_| Error::<T>::ProofTooLarge)?;
+            Self::ensure_not_paused(from_chain, to_chain)?;
+            let source = Self::ensure_chain_enabled(from_chain)?;
+            Self::ensure_chain_enabled(to_chain)?;
+            ensure!(payload.len() <= source.max_payload_size as usize, Error::<T>::PayloadTooLarge);
 
-            // Get and increment nonce
-            let nonce = Self::get_next_nonce(from_chain, &sender);
+            // Only configured routes accept messages, and only for allowed programs
+            let route = Routes::<T>::get(from_chain, to_chain).ok_or(Error::<T>::RouteNotFound)?;
+            ensure!(payload.len() <= route.max_payload_size as usize, Error::<T>::PayloadTooLarge);
+            ensure!(route.allows_program(&program_hash), Error::<T>::ProgramNotAllowed);
+            let deposit = route.deposit;
+
+            // Reject nonces that were already delivered on this lane
+            ensure!(
+                nonce >= InboundLaneNonces::<T>::get(from_chain, lane),
+                Error::<T>::NonceAlreadyUsed
+            );
+
+            // Enforce rate limits
+            Self::apply_rate_limits(&sender)?;
 
             // Create message
             let message = Message {
                 from_chain,
                 to_chain,
                 sender: sender.clone(),
+                source_sender,
+                program_hash,
                 payload,
+                lane,
                 nonce,
-                timestamp: T::BlockNumber::current().saturated_into::<u64>(),
+                timestamp: Self::current_block(),
                 status: MessageStatus::Pending,
                 proof,
+                key_version: None,
+                deposit,
             };
 
-            // Generate message hash
-            let hash = T::Hashing::hash_of(&message);
+            // Generate message ID
+            let hash = Self::message_id(&message);
+            ensure!(!Messages::<T>::contains_key(hash), Error::<T>::DuplicateMessage);
 
-            // Reserve deposit
-            T::Currency::reserve(&sender, T::MessageDeposit::get())?;
+            // Hold deposit
+            T::Currency::hold(&HoldReason::MessageDeposit.into(), &sender, deposit)?;
 
-            // Store message
+            // Escrow relayer reward
+            if let Some(reward) = reward.filter(|r| !r.is_zero()) {
+                T::Currency::hold(&HoldReason::RelayerReward.into(), &sender, reward)?;
+                MessageRewards::<T>::insert(hash, reward);
+            }
+
+            // Store message, keeping its program cached until it is verified
+            ProgramMessageCount::<T>::mutate(program_hash, |count| *count = count.saturating_add(1));
             Messages::<T>::insert(hash, message);
 
             // Emit event
@@ -241,6 +1215,8 @@ pub mod pallet {
                 hash,
                 from_chain,
                 to_chain,
+                lane,
+                nonce,
                 sender,
             });
 
@@ -248,147 +1224,91 @@ pub mod pallet {
         }
 
         /// Verify a submitted message
+        ///
+        /// On success the caller receives the message's relayer reward.
         #[pallet::call_index(1)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(T::WeightInfo::verify_message())]
         pub fn verify_message(
             origin: OriginFor<T>,
-            message_hash: T::Hash,
+            message_hash: MessageId,
         ) -> DispatchResult {
-            ensure_signed(origin)?;
-
-            // Get message
-            let mut message = Messages::<T>::get(message_hash)
-                .ok_or(Error::<T>::MessageNotFound)?;
-
-            // Check status
-            ensure!(message.status == MessageStatus::Pending, Error::<T>::InvalidStatusTransition);
-
-            // Get proof and verify
-            if let Some(proof) = &message.proof {
-                // Get verification key for the program
-                let program_hash = Self::compute_program_hash(&message);
-                let key_entry = VerificationKeys::<T>::get(program_hash)
-                    .ok_or(Error::<T>::InvalidKey)?;
-
-                // Create verification context
-                let context = VerificationContext {
-                    verifying_key: key_entry.key_bytes,
-                    program_hash,
-                };
-
-                // Create verification params
-                let params = VerificationParams {
-                    proof,
-                    payload: &message.payload,
-                    from_chain: message.from_chain as u64,
-                    to_chain: message.to_chain as u64,
-                    nonce: message.nonce,
-                    timestamp: message.timestamp,
-                };
-
-                // Verify proof
-                match verify_proof(&context, &params) {
-                    Ok(()) => {
-                        // Update status
-                        message.status = MessageStatus::Verified;
-                        Messages::<T>::insert(message_hash, message.clone());
-
-                        // Emit event
-                        Self::deposit_event(Event::MessageVerified {
-                            hash: message_hash,
-                            from_chain: message.from_chain,
-                            to_chain: message.to_chain,
-                        });
-                    }
-                    Err(e) => {
-                        // Update status to failed
-                        message.status = MessageStatus::Failed;
-                        Messages::<T>::insert(message_hash, message);
-
-                        // Convert error and emit event
-                        let error_bytes = match e {
-                            VerificationError::InvalidProofFormat => b"Invalid proof format".to_vec(),
-                            VerificationError::VerificationFailed => b"Verification failed".to_vec(),
-                            VerificationError::InvalidInput => b"Invalid input".to_vec(),
-                            VerificationError::SystemError => b"System error".to_vec(),
-                            VerificationError::Sp1Error(bytes) => bytes,
-                        };
+            let relayer = ensure_signed(origin)?;
 
-                        Self::deposit_event(Event::MessageVerificationFailed {
-                            hash: message_hash,
-                            error: error_bytes.clone(),
-                        });
+            Self::do_verify_message(&relayer, message_hash)
+        }
 
-                        // Map error type to pallet error
-                        match e {
-                            VerificationError::InvalidProofFormat => return Err(Error::<T>::InvalidProof.into()),
-                            VerificationError::VerificationFailed => return Err(Error::<T>::VerificationFailed.into()),
-                            VerificationError::InvalidInput => return Err(Error::<T>::InvalidChainId.into()),
-                            VerificationError::SystemError => return Err(Error::<T>::VerificationFailed.into()),
-                            VerificationError::Sp1Error(_) => return Err(Error::<T>::Sp1Error(error_bytes).into()),
-                        }
-                    }
-                }
-            }
+        /// Attach or replace the proof of a `Pending` or `Failed` message and verify it
+        ///
+        /// Any relayer may supply the proof. The sender's proofs are kept even
+        /// if they fail, up to `MaxProofAttempts` per message; proofs from
+        /// anyone else are only kept if they verify, so a failed third-party
+        /// proof neither fails the message nor counts as an attempt. On success
+        /// the caller receives the message's relayer reward.
+        #[pallet::call_index(4)]
+        #[pallet::weight(T::WeightInfo::attach_proof(proof.len() as u32))]
+        pub fn attach_proof(
+            origin: OriginFor<T>,
+            message_hash: MessageId,
+            proof: Vec<u8>,
+        ) -> DispatchResult {
+            let relayer = ensure_signed(origin)?;
+            let proof: BoundedVec<u8, T::MaxProofSize> =
+                proof.try_into().map_err(|_| Error::<T>::ProofTooLarge)?;
 
-            Ok(())
+            Self::do_attach_proof(&relayer, message_hash, proof)
         }
 
-        /// Add or update a verification key
+        /// Add a new version of a program's verification key
+        ///
+        /// The new version becomes active immediately, bypassing the activation
+        /// delay of `schedule_verification_key`, so only `EmergencyKeyOrigin`
+        /// may call this. The previously active version stays accepted for
+        /// `KeyGracePeriod` blocks so that proofs already in flight can still
+        /// be verified.
         #[pallet::call_index(2)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(T::WeightInfo::add_verification_key())]
         pub fn add_verification_key(
             origin: OriginFor<T>,
             program_hash: [u8; 32],
             key_bytes: Vec<u8>,
-            metadata: Option<Vec<u8>>,
+            metadata: Option<KeyMetadataInputOf<T>>,
         ) -> DispatchResult {
-            ensure_root(origin)?;
-
-            // Validate key size
-            ensure!(key_bytes.len() <= T::MaxKeySize::get() as usize, Error::<T>::KeyTooLarge);
-
-            // Create key entry
-            let key_entry = VerificationKeyEntry::new(
-                program_hash,
-                key_bytes,
-                T::BlockNumber::current().saturated_into::<u64>(),
-                metadata,
-            );
-
-            // Validate key format
-            key_entry.validate().map_err(|_| Error::<T>::InvalidKey)?;
+            T::EmergencyKeyOrigin::ensure_origin(origin.clone())?;
+            let added_by = ensure_signed(origin).ok();
 
-            // Store key
-            VerificationKeys::<T>::insert(program_hash, key_entry);
-
-            // Emit event
-            Self::deposit_event(Event::VerificationKeyAdded {
-                program_hash,
-            });
+            let key_entry = Self::new_key_entry(program_hash, key_bytes, added_by, metadata)?;
+            Self::store_verification_key(key_entry, Self::next_key_versions(&program_hash));
 
             Ok(())
         }
 
         /// Cache a program for verification
         #[pallet::call_index(3)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(T::WeightInfo::cache_program())]
         pub fn cache_program(
             origin: OriginFor<T>,
             program_hash: [u8; 32],
             program_bytes: Vec<u8>,
         ) -> DispatchResult {
-            ensure_root(origin)?;
+            T::ProgramAdminOrigin::ensure_origin(origin)?;
+
+            // Validate program size and hash
+            let program_bytes: BoundedVec<u8, T::MaxProgramSize> =
+                program_bytes.try_into().map_err(|_| Error::<T>::ProgramTooLarge)?;
+            ensure!(
+                T::ProgramHasher::program_hash(&program_bytes) == program_hash,
+                Error::<T>::ProgramHashMismatch
+            );
 
             // Create cache entry
             let entry = ProgramCacheEntry::new(
                 program_hash,
                 program_bytes,
-                T::BlockNumber::current().saturated_into::<u64>(),
+                Self::current_block(),
             );
 
             // Store program
-            ProgramCache::<T>::insert(program_hash, entry);
+            Self::insert_program(entry)?;
 
             // Emit event
             Self::deposit_event(Event::ProgramCached {
@@ -397,34 +1317,1642 @@ pub mod pallet {
 
             Ok(())
         }
-    }
 
-    impl<T: Config> Pallet<T> {
-        /// Get and increment nonce for a chain and account
-        fn get_next_nonce(chain_id: ChainId, account: &T::AccountId) -> u64 {
-            let nonce = Nonces::<T>::get(chain_id, account);
-            Nonces::<T>::insert(chain_id, account, nonce + 1);
-            nonce
-        }
-
-        /// Compute program hash for a message
-        fn compute_program_hash(message: &Message<T::AccountId>) -> [u8; 32] {
-            // TODO: Implement proper program hash computation
-            // For now, use a dummy hash based on chain IDs
-            let mut hash = [0u8; 32];
-            hash[0] = message.from_chain as u8;
-            hash[1] = message.to_chain as u8;
-            hash
-        }
-
-        /// Clean up old program cache entries
-        pub(crate) fn cleanup_program_cache() {
-            let current_block = T::BlockNumber::current().saturated_into::<u64>();
-            let max_age = T::MaxProgramAge::get() as u64;
+        /// Cancel a pending or failed message submitted by the caller
+        ///
+        /// The message is removed and its deposit and any escrowed reward are
+        /// returned. Failed messages can be cancelled so that a message whose
+        /// payload can never be proven does not hold funds until it expires.
+        #[pallet::call_index(5)]
+        #[pallet::weight(T::WeightInfo::cancel_message())]
+        pub fn cancel_message(
+            origin: OriginFor<T>,
+            message_hash: MessageId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let message = Messages::<T>::get(message_hash)
+                .ok_or(Error::<T>::MessageNotFound)?;
+            ensure!(message.sender == who, Error::<T>::NotMessageSender);
+
+            Self::do_cancel_message(message_hash, message)
+        }
+
+        /// Cancel any pending or failed message, e.g. one abandoned by its sender
+        #[pallet::call_index(6)]
+        #[pallet::weight(T::WeightInfo::force_cancel_message())]
+        pub fn force_cancel_message(
+            origin: OriginFor<T>,
+            message_hash: MessageId,
+        ) -> DispatchResult {
+            T::ForceOrigin::ensure_origin(origin)?;
+
+            let message = Messages::<T>::get(message_hash)
+                .ok_or(Error::<T>::MessageNotFound)?;
+
+            Self::do_cancel_message(message_hash, message)
+        }
+
+        /// Add a chain to the registry
+        #[pallet::call_index(7)]
+        #[pallet::weight(T::WeightInfo::register_chain())]
+        pub fn register_chain(
+            origin: OriginFor<T>,
+            chain_id: ChainId,
+            name: Vec<u8>,
+            max_payload_size: u32,
+        ) -> DispatchResult {
+            T::ChainAdminOrigin::ensure_origin(origin)?;
+
+            ensure!(!Chains::<T>::contains_key(chain_id), Error::<T>::ChainAlreadyRegistered);
+            let name = name.try_into().map_err(|_| Error::<T>::ChainNameTooLong)?;
+            ensure!(max_payload_size <= T::MaxPayloadSize::get(), Error::<T>::InvalidMaxPayloadSize);
+
+            Chains::<T>::insert(chain_id, ChainInfo {
+                name,
+                enabled: true,
+                max_payload_size,
+            });
+
+            Self::deposit_event(Event::ChainRegistered { chain_id });
+
+            Ok(())
+        }
+
+        /// Update the settings of a registered chain
+        ///
+        /// Fields passed as `None` are left unchanged.
+        #[pallet::call_index(8)]
+        #[pallet::weight(T::WeightInfo::update_chain())]
+        pub fn update_chain(
+            origin: OriginFor<T>,
+            chain_id: ChainId,
+            enabled: Option<bool>,
+            max_payload_size: Option<u32>,
+        ) -> DispatchResult {
+            T::ChainAdminOrigin::ensure_origin(origin)?;
+
+            Chains::<T>::try_mutate(chain_id, |maybe_info| -> DispatchResult {
+                let info = maybe_info.as_mut().ok_or(Error::<T>::ChainNotRegistered)?;
+                if let Some(enabled) = enabled {
+                    info.enabled = enabled;
+                }
+                if let Some(size) = max_payload_size {
+                    ensure!(size <= T::MaxPayloadSize::get(), Error::<T>::InvalidMaxPayloadSize);
+                    info.max_payload_size = size;
+                }
+                Ok(())
+            })?;
+
+            Self::deposit_event(Event::ChainUpdated { chain_id });
+
+            Ok(())
+        }
+
+        /// Remove a chain from the registry
+        ///
+        /// Routes from or to the chain, a pause of the chain and its volume
+        /// cap must be removed first. Chains that have received messages keep
+        /// their lane nonces, so that re-registering the chain cannot replay
+        /// messages, and can only be disabled.
+        #[pallet::call_index(9)]
+        #[pallet::weight(T::WeightInfo::deregister_chain())]
+        pub fn deregister_chain(
+            origin: OriginFor<T>,
+            chain_id: ChainId,
+        ) -> DispatchResult {
+            T::ChainAdminOrigin::ensure_origin(origin)?;
+
+            ensure!(Chains::<T>::contains_key(chain_id), Error::<T>::ChainNotRegistered);
+            ensure!(
+                ChainRouteCount::<T>::get(chain_id) == 0
+                    && !Paused::<T>::contains_key(PauseScope::Chain(chain_id))
+                    && !ChainVolumeLimits::<T>::contains_key(chain_id)
+                    && InboundLaneNonces::<T>::iter_prefix(chain_id).next().is_none(),
+                Error::<T>::ChainInUse
+            );
+            Chains::<T>::remove(chain_id);
+            ChainVolumeCounters::<T>::remove(chain_id);
+
+            Self::deposit_event(Event::ChainDeregistered { chain_id });
+
+            Ok(())
+        }
+
+        /// Configure the route from one chain to another
+        ///
+        /// Messages are only accepted on configured routes, for the programs in
+        /// the route's allowlist.
+        #[pallet::call_index(10)]
+        #[pallet::weight(T::WeightInfo::set_route())]
+        pub fn set_route(
+            origin: OriginFor<T>,
+            from_chain: ChainId,
+            to_chain: ChainId,
+            route: RouteConfigOf<T>,
+        ) -> DispatchResult {
+            T::RouteAdminOrigin::ensure_origin(origin)?;
+
+            ensure!(Chains::<T>::contains_key(from_chain), Error::<T>::ChainNotRegistered);
+            ensure!(Chains::<T>::contains_key(to_chain), Error::<T>::ChainNotRegistered);
+            ensure!(route.max_payload_size <= T::MaxPayloadSize::get(), Error::<T>::InvalidMaxPayloadSize);
+            ensure!(route.deposit >= T::MessageDeposit::get(), Error::<T>::DepositTooLow);
+
+            if !Routes::<T>::contains_key(from_chain, to_chain) {
+                Self::count_route(from_chain, to_chain);
+            }
+            Routes::<T>::insert(from_chain, to_chain, route);
+
+            Self::deposit_event(Event::RouteConfigured { from_chain, to_chain });
+
+            Ok(())
+        }
+
+        /// Remove a route configuration, closing the route to new messages
+        ///
+        /// A pause of the route is lifted with it.
+        #[pallet::call_index(11)]
+        #[pallet::weight(T::WeightInfo::remove_route())]
+        pub fn remove_route(
+            origin: OriginFor<T>,
+            from_chain: ChainId,
+            to_chain: ChainId,
+        ) -> DispatchResult {
+            T::RouteAdminOrigin::ensure_origin(origin)?;
+
+            ensure!(Routes::<T>::contains_key(from_chain, to_chain), Error::<T>::RouteNotFound);
+            Routes::<T>::remove(from_chain, to_chain);
+            Paused::<T>::remove(PauseScope::Route(from_chain, to_chain));
+            for chain_id in [from_chain, to_chain] {
+                ChainRouteCount::<T>::mutate(chain_id, |count| *count = count.saturating_sub(1));
+            }
+
+            Self::deposit_event(Event::RouteRemoved { from_chain, to_chain });
 
-            ProgramCache::<T>::retain(|_, entry| {
-                current_block.saturating_sub(entry.cached_at) < max_age
+            Ok(())
+        }
+
+        /// Pause submission and verification of messages within a scope
+        ///
+        /// Chains must be registered and routes configured to be paused.
+        /// Pausing an already paused scope replaces its record.
+        #[pallet::call_index(12)]
+        #[pallet::weight(T::WeightInfo::pause())]
+        pub fn pause(
+            origin: OriginFor<T>,
+            scope: PauseScope,
+            reason: Vec<u8>,
+        ) -> DispatchResult {
+            T::PauseOrigin::ensure_origin(origin.clone())?;
+            let paused_by = ensure_signed(origin).ok();
+
+            match scope {
+                PauseScope::Global => {}
+                PauseScope::Chain(chain_id) => {
+                    ensure!(Chains::<T>::contains_key(chain_id), Error::<T>::ChainNotRegistered)
+                }
+                PauseScope::Route(from_chain, to_chain) => {
+                    ensure!(Routes::<T>::contains_key(from_chain, to_chain), Error::<T>::RouteNotFound)
+                }
+            }
+            let bounded_reason = reason.clone().try_into().map_err(|_| Error::<T>::PauseReasonTooLong)?;
+            Paused::<T>::insert(scope, PauseRecord {
+                paused_by: paused_by.clone(),
+                reason: bounded_reason,
+                paused_at: Self::current_block(),
             });
+
+            Self::deposit_event(Event::BridgePaused { scope, paused_by, reason });
+
+            Ok(())
+        }
+
+        /// Lift a pause
+        #[pallet::call_index(13)]
+        #[pallet::weight(T::WeightInfo::unpause())]
+        pub fn unpause(
+            origin: OriginFor<T>,
+            scope: PauseScope,
+        ) -> DispatchResult {
+            T::PauseOrigin::ensure_origin(origin.clone())?;
+            let unpaused_by = ensure_signed(origin).ok();
+
+            ensure!(Paused::<T>::contains_key(scope), Error::<T>::NotPaused);
+            Paused::<T>::remove(scope);
+
+            Self::deposit_event(Event::BridgeUnpaused { scope, unpaused_by });
+
+            Ok(())
+        }
+
+        /// Set or clear the transfer volume cap of a source chain
+        #[pallet::call_index(14)]
+        #[pallet::weight(T::WeightInfo::set_volume_limit())]
+        pub fn set_volume_limit(
+            origin: OriginFor<T>,
+            chain_id: ChainId,
+            limit: Option<u128>,
+        ) -> DispatchResult {
+            T::RouteAdminOrigin::ensure_origin(origin)?;
+
+            ensure!(Chains::<T>::contains_key(chain_id), Error::<T>::ChainNotRegistered);
+            ChainVolumeLimits::<T>::set(chain_id, limit);
+
+            Self::deposit_event(Event::VolumeLimitSet { chain_id, limit });
+
+            Ok(())
+        }
+
+        /// Temporarily stop a verification key from verifying proofs
+        ///
+        /// Messages that would use the key stay pending until it is thawed or
+        /// another accepted version verifies them.
+        #[pallet::call_index(15)]
+        #[pallet::weight(T::WeightInfo::freeze_verification_key())]
+        pub fn freeze_verification_key(
+            origin: OriginFor<T>,
+            program_hash: [u8; 32],
+            version: KeyVersion,
+            reason: Vec<u8>,
+        ) -> DispatchResult {
+            T::KeyAdminOrigin::ensure_origin(origin)?;
+
+            let bounded_reason = reason.clone().try_into().map_err(|_| Error::<T>::ReasonTooLong)?;
+            VerificationKeys::<T>::try_mutate(program_hash, version, |entry| {
+                let entry = entry.as_mut().ok_or(Error::<T>::KeyNotFound)?;
+                entry
+                    .freeze(bounded_reason, Self::current_block())
+                    .map_err(|_| Error::<T>::InvalidKeyStatusTransition)
+            })?;
+
+            Self::deposit_event(Event::VerificationKeyFrozen { program_hash, version, reason });
+
+            Ok(())
+        }
+
+        /// Re-enable a frozen verification key
+        #[pallet::call_index(16)]
+        #[pallet::weight(T::WeightInfo::thaw_verification_key())]
+        pub fn thaw_verification_key(
+            origin: OriginFor<T>,
+            program_hash: [u8; 32],
+            version: KeyVersion,
+        ) -> DispatchResult {
+            T::KeyAdminOrigin::ensure_origin(origin)?;
+
+            VerificationKeys::<T>::try_mutate(program_hash, version, |entry| {
+                let entry = entry.as_mut().ok_or(Error::<T>::KeyNotFound)?;
+                entry.thaw().map_err(|_| Error::<T>::InvalidKeyStatusTransition)
+            })?;
+
+            Self::deposit_event(Event::VerificationKeyThawed { program_hash, version });
+
+            Ok(())
+        }
+
+        /// Permanently stop a verification key from verifying proofs
+        ///
+        /// Revocation cannot be undone; a replacement is added as a new version.
+        /// Once every accepted version of a program is revoked, its pending
+        /// messages can be failed with `fail_messages_for_revoked_key`.
+        #[pallet::call_index(17)]
+        #[pallet::weight(T::WeightInfo::revoke_verification_key())]
+        pub fn revoke_verification_key(
+            origin: OriginFor<T>,
+            program_hash: [u8; 32],
+            version: KeyVersion,
+            reason: Vec<u8>,
+        ) -> DispatchResult {
+            T::KeyAdminOrigin::ensure_origin(origin)?;
+
+            let bounded_reason = reason.clone().try_into().map_err(|_| Error::<T>::ReasonTooLong)?;
+            VerificationKeys::<T>::try_mutate(program_hash, version, |entry| {
+                let entry = entry.as_mut().ok_or(Error::<T>::KeyNotFound)?;
+                entry
+                    .revoke(bounded_reason, Self::current_block())
+                    .map_err(|_| Error::<T>::InvalidKeyStatusTransition)
+            })?;
+
+            Self::deposit_event(Event::VerificationKeyRevoked { program_hash, version, reason });
+
+            Ok(())
+        }
+
+        /// Fail pending messages of a program whose accepted keys are all revoked
+        ///
+        /// Callable by anyone. Messages of other programs or not pending are
+        /// skipped. Deposits stay held until the messages are cancelled or pruned.
+        #[pallet::call_index(18)]
+        #[pallet::weight(T::WeightInfo::fail_messages_for_revoked_key(message_ids.len() as u32))]
+        pub fn fail_messages_for_revoked_key(
+            origin: OriginFor<T>,
+            program_hash: [u8; 32],
+            message_ids: BoundedVec<MessageId, T::MaxRevokedMessageBatch>,
+        ) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let versions = Self::accepted_key_versions(&program_hash);
+            ensure!(!versions.is_empty(), Error::<T>::KeyNotFound);
+            ensure!(
+                versions.iter().all(|version| {
+                    VerificationKeys::<T>::get(program_hash, version).map_or(true, |entry| entry.is_revoked())
+                }),
+                Error::<T>::KeyNotRevoked
+            );
+
+            for id in message_ids {
+                Messages::<T>::mutate(id, |message| {
+                    let Some(message) = message else { return };
+                    if message.program_hash != program_hash || message.status != MessageStatus::Pending {
+                        return;
+                    }
+                    message.status = MessageStatus::Failed;
+                    Self::deposit_event(Event::MessageVerificationFailed {
+                        hash: id,
+                        error: b"Verification key revoked".to_vec(),
+                    });
+                });
+            }
+
+            Ok(())
+        }
+
+        /// Register a verification key for a program owned by the caller
+        ///
+        /// Works like `schedule_verification_key` without governance, holding
+        /// `KeyDeposit` from the caller: the key can be activated after
+        /// `KeyActivationDelay` blocks and cancelled by the caller, the key
+        /// admins or the pause origin until then. The first registration for a program
+        /// without keys proves ownership with the program bytes and records the
+        /// caller as owner, after a `commit_program_claim` in an earlier block;
+        /// later ones are made by the owner or carry the owner's signature over
+        /// [`crate::keys::key_registration_payload`] with the program's current
+        /// entry in `KeyRegistrationNonces`. Owners cannot rotate out
+        /// keys added by governance. Routes only use such keys if their key
+        /// policy allows it.
+        #[pallet::call_index(19)]
+        #[pallet::weight(T::WeightInfo::register_verification_key())]
+        pub fn register_verification_key(
+            origin: OriginFor<T>,
+            program_hash: [u8; 32],
+            key_bytes: Vec<u8>,
+            metadata: Option<KeyMetadataInputOf<T>>,
+            ownership: KeyOwnershipProofOf<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Check ownership
+            let versions = Self::next_key_versions(&program_hash);
+            let owner = ProgramOwners::<T>::get(program_hash);
+            match ownership {
+                KeyOwnershipProof::ProgramBytes(program_bytes) => {
+                    ensure!(
+                        program_bytes.len() <= T::MaxProgramSize::get() as usize,
+                        Error::<T>::ProgramTooLarge
+                    );
+                    ensure!(
+                        T::ProgramHasher::program_hash(&program_bytes) == program_hash,
+                        Error::<T>::InvalidOwnershipProof
+                    );
+                    match &owner {
+                        Some(owner) => ensure!(*owner == who, Error::<T>::NotProgramOwner),
+                        None => {
+                            // Programs with governance keys cannot be claimed
+                            ensure!(
+                                !ActiveKeyVersions::<T>::contains_key(program_hash)
+                                    && !ScheduledKeys::<T>::contains_key(program_hash),
+                                Error::<T>::NotProgramOwner
+                            );
+                            // Only a claim committed before the bytes were revealed counts
+                            let (commitment, committed_at, claim_deposit) =
+                                ProgramClaims::<T>::take(&who).ok_or(Error::<T>::ClaimNotCommitted)?;
+                            ensure!(
+                                commitment == program_claim_commitment(&who, &program_bytes)
+                                    && committed_at < Self::current_block()
+                                    && !Self::is_claim_expired(committed_at),
+                                Error::<T>::ClaimNotCommitted
+                            );
+                            T::Currency::release(
+                                &HoldReason::ProgramClaim.into(),
+                                &who,
+                                claim_deposit,
+                                Precision::BestEffort,
+                            )?;
+                        }
+                    }
+                }
+                KeyOwnershipProof::Signature(signature) => {
+                    let owner = owner.as_ref().ok_or(Error::<T>::NotProgramOwner)?;
+                    let nonce = KeyRegistrationNonces::<T>::get(program_hash);
+                    let payload = key_registration_payload(&program_hash, versions.active, nonce, &key_bytes);
+                    ensure!(signature.verify(&payload[..], owner), Error::<T>::InvalidOwnershipProof);
+                }
+            }
+
+            Self::ensure_no_governance_keys_accepted(&program_hash)?;
+            ensure!(!ScheduledKeys::<T>::contains_key(program_hash), Error::<T>::KeyAlreadyScheduled);
+
+            let key_entry = Self::new_key_entry(program_hash, key_bytes, Some(who.clone()), metadata)?;
+
+            // Hold deposit
+            let deposit = T::KeyDeposit::get();
+            T::Currency::hold(&HoldReason::KeyRegistration.into(), &who, deposit)?;
+            ScheduledKeyDeposits::<T>::insert(program_hash, (who.clone(), deposit));
+            KeyRegistrationNonces::<T>::mutate(program_hash, |nonce| *nonce = nonce.wrapping_add(1));
+
+            if owner.is_none() {
+                ProgramOwners::<T>::insert(program_hash, who.clone());
+                Self::deposit_event(Event::ProgramOwnerSet { program_hash, owner: who });
+            }
+
+            Self::schedule_key(key_entry);
+
+            Ok(())
+        }
+
+        /// Remove an owner-registered key and return its deposit
+        ///
+        /// Only the depositor can remove a key, and only once it is revoked or
+        /// no longer accepted after a rotation.
+        #[pallet::call_index(20)]
+        #[pallet::weight(T::WeightInfo::remove_verification_key())]
+        pub fn remove_verification_key(
+            origin: OriginFor<T>,
+            program_hash: [u8; 32],
+            version: KeyVersion,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let (depositor, deposit) = KeyDeposits::<T>::get(program_hash, version)
+                .ok_or(Error::<T>::KeyDepositNotFound)?;
+            ensure!(depositor == who, Error::<T>::NotProgramOwner);
+
+            let revoked = VerificationKeys::<T>::get(program_hash, version)
+                .map_or(true, |entry| entry.is_revoked());
+            ensure!(
+                revoked || !Self::accepted_key_versions(&program_hash).contains(&version),
+                Error::<T>::KeyStillInUse
+            );
+
+            VerificationKeys::<T>::remove(program_hash, version);
+            KeyDeposits::<T>::remove(program_hash, version);
+            let deposit_released = T::Currency::release(
+                &HoldReason::KeyRegistration.into(),
+                &who,
+                deposit,
+                Precision::BestEffort,
+            )?;
+
+            Self::deposit_event(Event::VerificationKeyRemoved { program_hash, version, deposit_released });
+
+            Ok(())
+        }
+
+        /// Schedule a new version of a program's verification key
+        ///
+        /// The key is stored in `ScheduledKeys` and can be activated with
+        /// `activate_scheduled_key` after `KeyActivationDelay` blocks. Until
+        /// then it does not verify proofs and can be cancelled.
+        #[pallet::call_index(21)]
+        #[pallet::weight(T::WeightInfo::schedule_verification_key())]
+        pub fn schedule_verification_key(
+            origin: OriginFor<T>,
+            program_hash: [u8; 32],
+            key_bytes: Vec<u8>,
+            metadata: Option<KeyMetadataInputOf<T>>,
+        ) -> DispatchResult {
+            T::KeyAdminOrigin::ensure_origin(origin.clone())?;
+            let added_by = ensure_signed(origin).ok();
+
+            ensure!(!ScheduledKeys::<T>::contains_key(program_hash), Error::<T>::KeyAlreadyScheduled);
+            let entry = Self::new_key_entry(program_hash, key_bytes, added_by, metadata)?;
+            Self::schedule_key(entry);
+
+            Ok(())
+        }
+
+        /// Cancel a scheduled key before it is activated
+        ///
+        /// Callable by `KeyAdminOrigin` or by `PauseOrigin`, so that watchers
+        /// who can pause the bridge can also stop a suspicious key, and by the
+        /// depositor of an owner-registered key, whose deposit is released.
+        #[pallet::call_index(22)]
+        #[pallet::weight(T::WeightInfo::cancel_scheduled_key())]
+        pub fn cancel_scheduled_key(
+            origin: OriginFor<T>,
+            program_hash: [u8; 32],
+        ) -> DispatchResult {
+            let deposit = ScheduledKeyDeposits::<T>::get(program_hash);
+            let is_depositor = match (ensure_signed(origin.clone()), &deposit) {
+                (Ok(who), Some((depositor, _))) => who == *depositor,
+                _ => false,
+            };
+            if !is_depositor {
+                T::KeyAdminOrigin::ensure_origin(origin.clone())
+                    .map(|_| ())
+                    .or_else(|_| T::PauseOrigin::ensure_origin(origin).map(|_| ()))?;
+            }
+
+            ensure!(ScheduledKeys::<T>::contains_key(program_hash), Error::<T>::NoScheduledKey);
+            ScheduledKeys::<T>::remove(program_hash);
+
+            if let Some((depositor, deposit)) = deposit {
+                ScheduledKeyDeposits::<T>::remove(program_hash);
+                let _ = T::Currency::release(
+                    &HoldReason::KeyRegistration.into(),
+                    &depositor,
+                    deposit,
+                    Precision::BestEffort,
+                );
+            }
+
+            Self::deposit_event(Event::ScheduledKeyCancelled { program_hash });
+
+            Ok(())
+        }
+
+        /// Activate a scheduled key once its delay has passed
+        ///
+        /// Callable by anyone. The key becomes a new version as with
+        /// `add_verification_key`, and the grace period of the replaced version
+        /// starts now. Owner-registered keys cannot be activated while keys
+        /// added by governance are accepted.
+        #[pallet::call_index(23)]
+        #[pallet::weight(T::WeightInfo::activate_scheduled_key())]
+        pub fn activate_scheduled_key(
+            origin: OriginFor<T>,
+            program_hash: [u8; 32],
+        ) -> DispatchResult {
+            ensure_signed(origin)?;
+
+            let scheduled = ScheduledKeys::<T>::get(program_hash).ok_or(Error::<T>::NoScheduledKey)?;
+            ensure!(scheduled.is_due(Self::current_block()), Error::<T>::ActivationNotDue);
+            ScheduledKeys::<T>::remove(program_hash);
+
+            let versions = Self::next_key_versions(&program_hash);
+            if let Some(deposit) = ScheduledKeyDeposits::<T>::take(program_hash) {
+                Self::ensure_no_governance_keys_accepted(&program_hash)?;
+                KeyDeposits::<T>::insert(program_hash, versions.active, deposit);
+            }
+
+            Self::store_verification_key(scheduled.entry, versions);
+
+            Ok(())
+        }
+
+        /// Begin a chunked upload of a program or proof
+        ///
+        /// `hash` is the program hash for programs and the blake2-256 hash for
+        /// proofs. Programs can only be uploaded once a key for them exists,
+        /// which is registered under the same program hash. `UploadDepositBase`
+        /// plus `UploadDepositPerByte` for each byte is held until the upload
+        /// is finalized, cancelled or times out after `UploadTimeout` blocks.
+        /// Finalizing a program keeps the per-byte part held until the
+        /// program is evicted from the cache.
+        #[pallet::call_index(24)]
+        #[pallet::weight(T::WeightInfo::begin_upload())]
+        pub fn begin_upload(
+            origin: OriginFor<T>,
+            target: UploadTarget,
+            hash: [u8; 32],
+            length: u32,
+        ) -> DispatchResult {
+            let owner = ensure_signed(origin)?;
+
+            ensure!(length > 0, Error::<T>::InvalidChunk);
+            match target {
+                UploadTarget::Program => {
+                    ensure!(length <= T::MaxProgramSize::get(), Error::<T>::ProgramTooLarge);
+                    ensure!(
+                        ActiveKeyVersions::<T>::contains_key(hash) || ScheduledKeys::<T>::contains_key(hash),
+                        Error::<T>::NoKeyForProgram
+                    );
+                    ensure!(!ProgramCacheUsage::<T>::contains_key(hash), Error::<T>::ProgramAlreadyCached);
+                }
+                UploadTarget::Proof(message_hash) => {
+                    ensure!(length <= T::MaxProofSize::get(), Error::<T>::ProofTooLarge);
+                    ensure!(Messages::<T>::contains_key(message_hash), Error::<T>::MessageNotFound);
+                }
+            }
+
+            // Hold deposit
+            let deposit = T::UploadDepositBase::get()
+                .saturating_add(T::UploadDepositPerByte::get().saturating_mul(length.into()));
+            T::Currency::hold(&HoldReason::Upload.into(), &owner, deposit)?;
+
+            let upload_id = NextUploadId::<T>::mutate(|next| {
+                let id = *next;
+                *next = next.saturating_add(1);
+                id
+            });
+            Uploads::<T>::insert(upload_id, Upload {
+                owner: owner.clone(),
+                target,
+                hash,
+                length,
+                received: 0,
+                chunks: 0,
+                deposit,
+                expires_at: Self::current_block().saturating_add(T::UploadTimeout::get() as u64),
+            });
+
+            Self::deposit_event(Event::UploadStarted { upload_id, owner, target, length });
+
+            Ok(())
+        }
+
+        /// Append the next chunk to an upload
+        ///
+        /// Chunks must be `MaxUploadChunkSize` bytes, except the last one.
+        #[pallet::call_index(25)]
+        #[pallet::weight(T::WeightInfo::append_chunk())]
+        pub fn append_chunk(
+            origin: OriginFor<T>,
+            upload_id: UploadId,
+            chunk: BoundedVec<u8, T::MaxUploadChunkSize>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut upload = Uploads::<T>::get(upload_id).ok_or(Error::<T>::UploadNotFound)?;
+            ensure!(upload.owner == who, Error::<T>::NotUploadOwner);
+            ensure!(!upload.is_expired(Self::current_block()), Error::<T>::UploadExpired);
+            let len = chunk.len() as u32;
+            ensure!(upload.accepts_chunk(len, T::MaxUploadChunkSize::get()), Error::<T>::InvalidChunk);
+
+            UploadChunks::<T>::insert(upload_id, upload.chunks, chunk);
+            upload.record_chunk(len);
+            let received = upload.received;
+            Uploads::<T>::insert(upload_id, upload);
+
+            Self::deposit_event(Event::UploadChunkAppended { upload_id, received });
+
+            Ok(())
+        }
+
+        /// Check a complete upload against its hash and move the data to its target
+        ///
+        /// Programs are cached and `UploadDepositPerByte` for each byte stays
+        /// held as a storage deposit until the program is evicted; proofs are
+        /// attached and verified as with `attach_proof`. The rest of the
+        /// deposit is released.
+        #[pallet::call_index(26)]
+        #[pallet::weight(
+            T::WeightInfo::finalize_upload(Pallet::<T>::max_upload_chunks())
+                .saturating_add(T::WeightInfo::attach_proof(T::MaxProofSize::get()))
+        )]
+        pub fn finalize_upload(
+            origin: OriginFor<T>,
+            upload_id: UploadId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let upload = Uploads::<T>::get(upload_id).ok_or(Error::<T>::UploadNotFound)?;
+            ensure!(upload.owner == who, Error::<T>::NotUploadOwner);
+            ensure!(!upload.is_expired(Self::current_block()), Error::<T>::UploadExpired);
+            ensure!(upload.is_complete(), Error::<T>::UploadIncomplete);
+
+            let mut data = Vec::with_capacity(upload.length as usize);
+            for index in 0..upload.chunks {
+                let chunk = UploadChunks::<T>::get(upload_id, index).ok_or(Error::<T>::UploadIncomplete)?;
+                data.extend_from_slice(&chunk);
+            }
+            Self::remove_upload(upload_id, &upload);
+
+            match upload.target {
+                UploadTarget::Program => {
+                    ensure!(
+                        T::ProgramHasher::program_hash(&data) == upload.hash,
+                        Error::<T>::UploadHashMismatch
+                    );
+                    ensure!(!ProgramCacheUsage::<T>::contains_key(upload.hash), Error::<T>::ProgramAlreadyCached);
+                    let bytes = data.try_into().map_err(|_| Error::<T>::ProgramTooLarge)?;
+                    Self::insert_program(ProgramCacheEntry::new(upload.hash, bytes, Self::current_block()))?;
+
+                    // The program's storage stays paid for while it is cached
+                    let deposit = T::UploadDepositPerByte::get().saturating_mul(upload.length.into());
+                    T::Currency::hold(&HoldReason::ProgramStorage.into(), &who, deposit)?;
+                    ProgramDeposits::<T>::insert(upload.hash, (who.clone(), deposit));
+                    Self::deposit_event(Event::ProgramCached { program_hash: upload.hash });
+                }
+                UploadTarget::Proof(message_hash) => {
+                    ensure!(blake2_256(&data) == upload.hash, Error::<T>::UploadHashMismatch);
+                    let proof = data.try_into().map_err(|_| Error::<T>::ProofTooLarge)?;
+                    Self::deposit_event(Event::UploadFinalized { upload_id, target: upload.target });
+                    return Self::do_attach_proof(&who, message_hash, proof);
+                }
+            }
+
+            Self::deposit_event(Event::UploadFinalized { upload_id, target: upload.target });
+
+            Ok(())
+        }
+
+        /// Cancel an unfinished upload and release its deposit
+        #[pallet::call_index(27)]
+        #[pallet::weight(T::WeightInfo::cancel_upload(Pallet::<T>::max_upload_chunks()))]
+        pub fn cancel_upload(
+            origin: OriginFor<T>,
+            upload_id: UploadId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let upload = Uploads::<T>::get(upload_id).ok_or(Error::<T>::UploadNotFound)?;
+            ensure!(upload.owner == who, Error::<T>::NotUploadOwner);
+            let deposit_released = Self::remove_upload(upload_id, &upload);
+
+            Self::deposit_event(Event::UploadRemoved { upload_id, expired: false, deposit_released });
+
+            Ok(())
+        }
+
+        /// Commit to claiming a program with its bytes in a later block
+        ///
+        /// `commitment` is [`crate::keys::program_claim_commitment`] of the
+        /// caller and the program bytes. It replaces any earlier commitment of
+        /// the caller and is consumed by `register_verification_key` within
+        /// `ClaimTimeout` blocks. `ClaimDeposit` is held until then, and
+        /// released when the claim is revealed, replaced or expires.
+        #[pallet::call_index(28)]
+        #[pallet::weight(T::WeightInfo::commit_program_claim())]
+        pub fn commit_program_claim(
+            origin: OriginFor<T>,
+            commitment: [u8; 32],
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            if let Some((_, _, deposit)) = ProgramClaims::<T>::get(&who) {
+                T::Currency::release(&HoldReason::ProgramClaim.into(), &who, deposit, Precision::BestEffort)?;
+            }
+            let deposit = T::ClaimDeposit::get();
+            T::Currency::hold(&HoldReason::ProgramClaim.into(), &who, deposit)?;
+            ProgramClaims::<T>::insert(&who, (commitment, Self::current_block(), deposit));
+
+            Self::deposit_event(Event::ProgramClaimCommitted { who });
+
+            Ok(())
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// Canonical ID of a message, see [`crate::message_id`]
+        pub fn message_id(message: &MessageOf<T>) -> MessageId {
+            T::MessageHasher::message_id(&MessageIdFields::new(
+                message.from_chain,
+                message.to_chain,
+                message.lane,
+                message.nonce,
+                message.source_sender,
+                message.program_hash,
+                T::MessageHasher::payload_hash(&message.payload),
+            ))
+        }
+
+        /// Build and validate a key entry from call arguments
+        fn new_key_entry(
+            program_hash: [u8; 32],
+            key_bytes: Vec<u8>,
+            added_by: Option<T::AccountId>,
+            metadata: Option<KeyMetadataInputOf<T>>,
+        ) -> Result<VerificationKeyEntryOf<T>, DispatchError> {
+            // Validate key and metadata sizes
+            let key_bytes: BoundedVec<u8, T::MaxKeySize> =
+                key_bytes.try_into().map_err(|_| Error::<T>::KeyTooLarge)?;
+            let metadata = metadata
+                .map(|metadata| metadata.rebound().ok_or(Error::<T>::MetadataTooLarge))
+                .transpose()?;
+
+            // Create key entry
+            let prepared_key = Self::prepare_key(&key_bytes)?;
+            let key_entry = VerificationKeyEntry::new(
+                program_hash,
+                key_bytes,
+                prepared_key,
+                Self::current_block(),
+                added_by,
+                metadata,
+            );
+
+            // Validate key format and derivation
+            key_entry.validate().map_err(|_| Error::<T>::InvalidKey)?;
+            Self::ensure_key_derivation(&key_entry)?;
+
+            Ok(key_entry)
+        }
+
+        /// Parse and preprocess a key for its backend
+        fn prepare_key(key_bytes: &[u8]) -> Result<BoundedVec<u8, T::MaxPreparedKeySize>, DispatchError> {
+            let prepared = T::KeyPreparer::prepare(key_bytes).map_err(|_| Error::<T>::InvalidKey)?;
+            Ok(prepared.try_into().map_err(|_| Error::<T>::KeyTooLarge)?)
+        }
+
+        /// Fail unless the key's program hash is derived from its bytes
+        fn ensure_key_derivation(key_entry: &VerificationKeyEntryOf<T>) -> DispatchResult {
+            let derived = T::ProgramHasher::key_program_hash(&key_entry.key_bytes)
+                .ok_or(Error::<T>::InvalidKey)?;
+            ensure!(derived == key_entry.program_hash, Error::<T>::ProgramHashMismatch);
+            Ok(())
+        }
+
+        /// Key versions of a program after adding a new key
+        ///
+        /// The currently active version stays accepted for `KeyGracePeriod` blocks.
+        fn next_key_versions(program_hash: &[u8; 32]) -> KeyVersionInfo {
+            let grace_until = Self::current_block().saturating_add(T::KeyGracePeriod::get() as u64);
+            ActiveKeyVersions::<T>::get(program_hash)
+                .map_or_else(KeyVersionInfo::initial, |info| info.rotate(grace_until))
+        }
+
+        /// Schedule a key for activation after `KeyActivationDelay` blocks
+        fn schedule_key(entry: VerificationKeyEntryOf<T>) {
+            let program_hash = entry.program_hash;
+            let activate_at = Self::current_block().saturating_add(T::KeyActivationDelay::get() as u64);
+            ScheduledKeys::<T>::insert(program_hash, ScheduledKey { entry, activate_at });
+
+            Self::deposit_event(Event::VerificationKeyScheduled { program_hash, activate_at });
+        }
+
+        /// Fail if a key added by governance is accepted for the program
+        ///
+        /// Owner-registered keys must not rotate such keys out.
+        fn ensure_no_governance_keys_accepted(program_hash: &[u8; 32]) -> DispatchResult {
+            ensure!(
+                Self::accepted_key_versions(program_hash)
+                    .iter()
+                    .all(|version| KeyDeposits::<T>::contains_key(program_hash, version)),
+                Error::<T>::GovernanceKeyActive
+            );
+            Ok(())
+        }
+
+        /// Store a key under the active version of `versions` and activate it
+        fn store_verification_key(key_entry: VerificationKeyEntryOf<T>, versions: KeyVersionInfo) {
+            let program_hash = key_entry.program_hash;
+            VerificationKeys::<T>::insert(program_hash, versions.active, key_entry);
+            ActiveKeyVersions::<T>::insert(program_hash, versions);
+
+            Self::deposit_event(Event::VerificationKeyAdded {
+                program_hash,
+                version: versions.active,
+                previous: versions.previous.map(|v| (v, versions.previous_valid_until)),
+            });
+        }
+
+        /// All stored versions of a program's verification key
+        pub fn verification_keys(program_hash: [u8; 32]) -> Vec<(KeyVersion, VerificationKeyEntryOf<T>)> {
+            VerificationKeys::<T>::iter_prefix(program_hash).collect()
+        }
+
+        /// All stored verification keys of all programs
+        pub fn all_verification_keys() -> Vec<([u8; 32], KeyVersion, VerificationKeyEntryOf<T>)> {
+            VerificationKeys::<T>::iter().collect()
+        }
+
+        /// Key versions currently accepted for a program, active first
+        pub fn accepted_key_versions(program_hash: &[u8; 32]) -> Vec<KeyVersion> {
+            ActiveKeyVersions::<T>::get(program_hash)
+                .map(|info| info.accepted_at(Self::current_block()))
+                .unwrap_or_default()
+        }
+
+        /// Whether messages from `from_chain` to `to_chain` are paused
+        pub fn is_paused(from_chain: ChainId, to_chain: ChainId) -> bool {
+            [
+                PauseScope::Global,
+                PauseScope::Chain(from_chain),
+                PauseScope::Chain(to_chain),
+                PauseScope::Route(from_chain, to_chain),
+            ]
+            .iter()
+            .any(|scope| Paused::<T>::contains_key(scope))
+        }
+
+        /// Fail with `Paused` if messages on the route are paused
+        fn ensure_not_paused(from_chain: ChainId, to_chain: ChainId) -> DispatchResult {
+            ensure!(!Self::is_paused(from_chain, to_chain), Error::<T>::Paused);
+            Ok(())
+        }
+
+        /// Count a submitted message against the block and sender limits
+        fn apply_rate_limits(sender: &T::AccountId) -> DispatchResult {
+            let now = Self::current_block();
+            let window = T::RateLimitWindow::get() as u64;
+
+            // Messages per block
+            let (block, count) = BlockMessageCount::<T>::get();
+            let count = if block == now { count } else { 0 };
+            ensure!(count < T::MaxMessagesPerBlock::get(), Error::<T>::RateLimited);
+            BlockMessageCount::<T>::put((now, count + 1));
+
+            // Messages per sender
+            let counter = SenderRateCounters::<T>::get(sender)
+                .try_add(1, T::MaxMessagesPerSenderWindow::get() as u128, now, window)
+                .ok_or(Error::<T>::RateLimited)?;
+            SenderRateCounters::<T>::insert(sender, counter);
+
+            Ok(())
+        }
+
+        /// Count the transfer volume of a message being verified against its source chain cap
+        fn charge_volume(from_chain: ChainId, payload: &[u8]) -> DispatchResult {
+            if let Some(limit) = ChainVolumeLimits::<T>::get(from_chain) {
+                if let Some(value) = T::PayloadValue::transfer_value(from_chain, payload) {
+                    let counter = ChainVolumeCounters::<T>::get(from_chain)
+                        .try_add(value, limit, Self::current_block(), T::RateLimitWindow::get() as u64)
+                        .ok_or(Error::<T>::RateLimited)?;
+                    ChainVolumeCounters::<T>::insert(from_chain, counter);
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Registry entry of an enabled chain
+        fn ensure_chain_enabled(chain_id: ChainId) -> Result<ChainInfoOf<T>, DispatchError> {
+            let info = Chains::<T>::get(chain_id).ok_or(Error::<T>::ChainNotRegistered)?;
+            ensure!(info.enabled, Error::<T>::ChainDisabled);
+            Ok(info)
+        }
+
+        /// Current block number as stored in message timestamps
+        fn current_block() -> u64 {
+            frame_system::Pallet::<T>::block_number().saturated_into::<u64>()
+        }
+
+        /// Whether a message has outlived `MessageTtl`
+        fn is_expired(message: &MessageOf<T>) -> bool {
+            Self::current_block().saturating_sub(message.timestamp) >= T::MessageTtl::get() as u64
+        }
+
+        /// Count a new route against both of its chains
+        fn count_route(from_chain: ChainId, to_chain: ChainId) {
+            for chain_id in [from_chain, to_chain] {
+                ChainRouteCount::<T>::mutate(chain_id, |count| *count = count.saturating_add(1));
+            }
+        }
+
+        /// Remove a message and release everything held for it
+        ///
+        /// Returns the message deposit released back to the sender.
+        fn remove_message(hash: MessageId, message: &MessageOf<T>) -> BalanceOf<T> {
+            Messages::<T>::remove(hash);
+            ProofAttempts::<T>::remove(hash);
+            if message.status != MessageStatus::Verified {
+                Self::unpin_program(&message.program_hash);
+            }
+
+            if let Some(reward) = MessageRewards::<T>::take(hash) {
+                let _ = T::Currency::release(
+                    &HoldReason::RelayerReward.into(),
+                    &message.sender,
+                    reward,
+                    Precision::BestEffort,
+                );
+            }
+
+            T::Currency::release(
+                &HoldReason::MessageDeposit.into(),
+                &message.sender,
+                message.deposit,
+                Precision::BestEffort,
+            )
+            .unwrap_or_else(|_| Zero::zero())
+        }
+
+        /// Remove a pending or failed message on behalf of its sender or governance
+        fn do_cancel_message(
+            message_hash: MessageId,
+            message: MessageOf<T>,
+        ) -> DispatchResult {
+            ensure!(
+                matches!(message.status, MessageStatus::Pending | MessageStatus::Failed),
+                Error::<T>::InvalidStatusTransition
+            );
+
+            let deposit_released = Self::remove_message(message_hash, &message);
+
+            Self::deposit_event(Event::MessageCancelled {
+                hash: message_hash,
+                deposit_released,
+            });
+
+            Ok(())
+        }
+
+        /// Prune expired messages within the given weight budget
+        ///
+        /// Iteration resumes from `PruneCursor`, so the whole map is swept
+        /// incrementally across blocks.
+        fn prune_messages(remaining_weight: Weight) -> Weight {
+            let db = T::DbWeight::get();
+            // Cursor read and write
+            let mut used = db.reads_writes(1, 1);
+            // Message and reward reads, removals and two hold releases
+            let per_message = db.reads_writes(4, 4);
+
+            if !used.saturating_add(per_message).all_lte(remaining_weight) {
+                return Weight::zero();
+            }
+
+            let mut iter = match PruneCursor::<T>::get() {
+                Some(cursor) => Messages::<T>::iter_from(cursor.into_inner()),
+                None => Messages::<T>::iter(),
+            };
+
+            let mut expired = Vec::new();
+            let mut finished = false;
+            while used.saturating_add(per_message).all_lte(remaining_weight) {
+                match iter.next() {
+                    Some((hash, message)) => {
+                        used = used.saturating_add(per_message);
+                        if Self::is_expired(&message) {
+                            expired.push((hash, message));
+                        }
+                    }
+                    None => {
+                        finished = true;
+                        break;
+                    }
+                }
+            }
+
+            if finished {
+                PruneCursor::<T>::kill();
+            } else {
+                // A key longer than the bound only restarts the sweep
+                PruneCursor::<T>::set(BoundedVec::try_from(iter.last_raw_key().to_vec()).ok());
+            }
+
+            for (hash, message) in expired {
+                let deposit_released = Self::remove_message(hash, &message);
+                Self::deposit_event(Event::MessagePruned {
+                    hash,
+                    status: message.status,
+                    deposit_released,
+                });
+            }
+
+            used
+        }
+
+        /// Attach or replace the proof of a `Pending` or `Failed` message and verify it
+        ///
+        /// Must run in a transactional context: a failed proof from anyone but
+        /// the sender returns an error and relies on the rollback.
+        fn do_attach_proof(
+            relayer: &T::AccountId,
+            message_hash: MessageId,
+            proof: BoundedVec<u8, T::MaxProofSize>,
+        ) -> DispatchResult {
+            // Get message
+            let mut message = Messages::<T>::get(message_hash)
+                .ok_or(Error::<T>::MessageNotFound)?;
+
+            // Check status
+            ensure!(
+                matches!(message.status, MessageStatus::Pending | MessageStatus::Failed),
+                Error::<T>::InvalidStatusTransition
+            );
+            ensure!(!Self::is_expired(&message), Error::<T>::MessageExpired);
+
+            // Count the attempt, which is reverted below unless the sender made it
+            let attempt = ProofAttempts::<T>::get(message_hash).saturating_add(1);
+            ensure!(attempt <= T::MaxProofAttempts::get(), Error::<T>::TooManyProofAttempts);
+            ProofAttempts::<T>::insert(message_hash, attempt);
+            let by_sender = *relayer == message.sender;
+
+            // Replace proof and reset status
+            message.proof = Some(proof);
+            message.status = MessageStatus::Pending;
+            Messages::<T>::insert(message_hash, message);
+
+            Self::deposit_event(Event::ProofAttached {
+                hash: message_hash,
+                relayer: relayer.clone(),
+                attempt,
+            });
+
+            Self::do_verify_message(relayer, message_hash)?;
+
+            // Failed proofs from third parties are rejected, reverting the attempt
+            if !by_sender {
+                let verified = Messages::<T>::get(message_hash)
+                    .map_or(false, |message| message.status == MessageStatus::Verified);
+                ensure!(verified, Error::<T>::VerificationFailed);
+            }
+
+            Ok(())
+        }
+
+        /// Verify a `Pending` message and record the outcome
+        ///
+        /// A rejected proof is not a dispatch error: the message is marked
+        /// `Failed` and kept so that a new proof can be attached. A missing
+        /// program is, so the message stays `Pending` until it is cached.
+        fn do_verify_message(relayer: &T::AccountId, message_hash: MessageId) -> DispatchResult {
+            // Get message
+            let mut message = Messages::<T>::get(message_hash)
+                .ok_or(Error::<T>::MessageNotFound)?;
+
+            // Check status
+            ensure!(message.status == MessageStatus::Pending, Error::<T>::InvalidStatusTransition);
+            ensure!(!Self::is_expired(&message), Error::<T>::MessageExpired);
+
+            // Route must not be paused and source chain must still be enabled
+            Self::ensure_not_paused(message.from_chain, message.to_chain)?;
+            Self::ensure_chain_enabled(message.from_chain)?;
+
+            // Route must still be configured and allow the program
+            let route = Routes::<T>::get(message.from_chain, message.to_chain)
+                .ok_or(Error::<T>::RouteNotFound)?;
+            ensure!(route.allows_program(&message.program_hash), Error::<T>::ProgramNotAllowed);
+            let (verification_mode, key_policy) = (route.verification_mode, route.key_policy);
+
+            // Messages are delivered strictly in order per lane; later ones stay pending
+            let expected_nonce = InboundLaneNonces::<T>::get(message.from_chain, message.lane);
+            ensure!(message.nonce == expected_nonce, Error::<T>::MessageOutOfOrder);
+
+            // Get proof and verify
+            if let (Some(proof), VerificationMode::Sp1) = (&message.proof, verification_mode) {
+                // Get accepted key versions for the program
+                let program_hash = message.program_hash;
+                let versions = Self::accepted_key_versions(&program_hash);
+                ensure!(!versions.is_empty(), Error::<T>::InvalidKey);
+
+                // Public inputs the proof must commit to
+                let public_inputs = encode_public_inputs::<T::MessageHasher>(
+                    message.from_chain,
+                    message.to_chain,
+                    message.lane,
+                    message.nonce,
+                    message.source_sender,
+                    message.program_hash,
+                    &message.payload,
+                );
+
+                // Create verification params
+                let params = VerificationParams {
+                    proof,
+                    input: &public_inputs,
+                    from_chain: message.from_chain as u64,
+                    to_chain: message.to_chain as u64,
+                    lane: message.lane,
+                    nonce: message.nonce,
+                    timestamp: message.timestamp,
+                };
+
+                // Frozen and revoked keys do not verify proofs
+                let keys: Vec<_> = versions
+                    .into_iter()
+                    .filter_map(|version| {
+                        VerificationKeys::<T>::get(program_hash, version)
+                            .filter(|entry| entry.is_active())
+                            .map(|entry| (version, entry))
+                    })
+                    .collect();
+                ensure!(!keys.is_empty(), Error::<T>::KeyNotActive);
+
+                // Owner-registered keys only verify on routes that allow them
+                let keys: Vec<_> = keys
+                    .into_iter()
+                    .filter(|(version, _)| {
+                        key_policy == KeyPolicy::AllowPermissionless
+                            || !KeyDeposits::<T>::contains_key(program_hash, version)
+                    })
+                    .collect();
+                ensure!(!keys.is_empty(), Error::<T>::KeyNotPermitted);
+
+                // Load the program from the cache and record its use, unless
+                // the backend identifies programs by the key alone
+                let program = if T::ProofVerifier::requires_program() {
+                    let entry = ProgramCache::<T>::get(program_hash).ok_or(Error::<T>::ProgramNotFound)?;
+                    let now = Self::current_block();
+                    ProgramCacheUsage::<T>::mutate(program_hash, |usage| {
+                        if let Some(usage) = usage {
+                            usage.record_use(now);
+                        }
+                    });
+                    entry.bytes.into_inner()
+                } else {
+                    Vec::new()
+                };
+
+                // Verify proof against each accepted key, active first
+                let mut outcome = Err(VerificationError::VerificationFailed);
+                for (version, key_entry) in keys {
+                    // Create verification context
+                    let context = VerificationContext::new(&program, program_hash)
+                        .with_verifying_key(key_entry.prepared_key.into_inner());
+
+                    outcome = verify_proof::<T::ProofVerifier>(&context, &params).map(|()| version);
+                    if outcome.is_ok() {
+                        break;
+                    }
+                }
+
+                match outcome {
+                    Ok(key_version) => {
+                        // Verified transfers count against the source chain cap,
+                        // otherwise the message stays pending until capacity frees up
+                        Self::charge_volume(message.from_chain, &message.payload)?;
+
+                        // Update status
+                        message.status = MessageStatus::Verified;
+                        message.key_version = Some(key_version);
+                        Messages::<T>::insert(message_hash, message.clone());
+                        Self::unpin_program(&program_hash);
+
+                        // Advance the lane
+                        InboundLaneNonces::<T>::insert(
+                            message.from_chain,
+                            message.lane,
+                            expected_nonce.saturating_add(1),
+                        );
+
+                        // Emit event
+                        Self::deposit_event(Event::MessageVerified {
+                            hash: message_hash,
+                            from_chain: message.from_chain,
+                            to_chain: message.to_chain,
+                            key_version,
+                        });
+
+                        // Reward the relayer
+                        Self::pay_relayer_reward(message_hash, &message.sender, relayer);
+                    }
+                    Err(e) => {
+                        // Update status to failed
+                        message.status = MessageStatus::Failed;
+                        Messages::<T>::insert(message_hash, message);
+
+                        // Convert error and emit event
+                        let error_bytes = match e {
+                            VerificationError::InvalidProofFormat => b"Invalid proof format".to_vec(),
+                            VerificationError::VerificationFailed => b"Verification failed".to_vec(),
+                            VerificationError::InvalidInput => b"Invalid input".to_vec(),
+                            VerificationError::SystemError => b"System error".to_vec(),
+                            VerificationError::BackendError(bytes) => bytes,
+                        };
+
+                        Self::deposit_event(Event::MessageVerificationFailed {
+                            hash: message_hash,
+                            error: error_bytes,
+                        });
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        /// The account holding the relayer reward fund
+        pub fn reward_fund_account() -> T::AccountId {
+            T::PalletId::get().into_account_truncating()
+        }
+
+        /// Pay the relayer reward for a verified message
+        ///
+        /// Uses the reward escrowed by the submitter if there is one, otherwise
+        /// `RelayerReward` from the fund account. A failed payment is logged and
+        /// does not affect the verification outcome.
+        fn pay_relayer_reward(message_hash: MessageId, sender: &T::AccountId, relayer: &T::AccountId) {
+            let paid = match MessageRewards::<T>::take(message_hash) {
+                Some(reward) => T::Currency::transfer_on_hold(
+                    &HoldReason::RelayerReward.into(),
+                    sender,
+                    relayer,
+                    reward,
+                    Precision::BestEffort,
+                    Restriction::Free,
+                    Fortitude::Polite,
+                ),
+                None => {
+                    let reward = T::RelayerReward::get();
+                    if reward.is_zero() {
+                        return;
+                    }
+                    <T::Currency as Mutate<T::AccountId>>::transfer(
+                        &Self::reward_fund_account(),
+                        relayer,
+                        reward,
+                        Preservation::Preserve,
+                    )
+                }
+            };
+
+            match paid {
+                Ok(amount) => Self::deposit_event(Event::RelayerRewarded {
+                    hash: message_hash,
+                    relayer: relayer.clone(),
+                    amount,
+                }),
+                Err(e) => log::warn!(
+                    target: crate::LOG_TARGET,
+                    "failed to reward relayer for message {:?}: {:?}",
+                    message_hash,
+                    e,
+                ),
+            }
+        }
+
+        /// Store a program in the cache, replacing any entry with the same hash
+        ///
+        /// Fails with `ProgramCacheFull` if the cache would exceed `MaxProgramCacheBytes`.
+        fn insert_program(entry: ProgramCacheEntryOf<T>) -> DispatchResult {
+            let added = entry.bytes.len() as u64;
+            let replaced = ProgramCacheUsage::<T>::get(entry.hash).map_or(0, |old| old.size as u64);
+            let total = ProgramCacheBytes::<T>::get().saturating_sub(replaced).saturating_add(added);
+            ensure!(total <= T::MaxProgramCacheBytes::get(), Error::<T>::ProgramCacheFull);
+            ProgramCacheBytes::<T>::put(total);
+            ProgramCacheUsage::<T>::insert(entry.hash, ProgramUsage::new(added as u32, entry.cached_at));
+            ProgramCache::<T>::insert(entry.hash, entry);
+            Ok(())
+        }
+
+        /// Whether the program must stay cached, protecting it from eviction
+        ///
+        /// Programs are pinned while an active key of the program is accepted
+        /// or unverified messages need them.
+        pub fn is_program_pinned(program_hash: &[u8; 32]) -> bool {
+            ProgramMessageCount::<T>::contains_key(program_hash)
+                || Self::accepted_key_versions(program_hash).into_iter().any(|version| {
+                    VerificationKeys::<T>::get(program_hash, version).map_or(false, |entry| entry.is_active())
+                })
+        }
+
+        /// Release the pin of one unverified message on its program
+        fn unpin_program(program_hash: &[u8; 32]) {
+            ProgramMessageCount::<T>::mutate_exists(program_hash, |count| {
+                *count = count.and_then(|count| count.checked_sub(1)).filter(|count| *count > 0);
+            });
+        }
+
+        /// Evict unused programs within the given weight budget
+        ///
+        /// Iteration resumes from `EvictionCursor`, so the whole cache is swept
+        /// incrementally across blocks. Programs pinned by an active key or
+        /// by unverified messages are never evicted; others are evicted according to
+        /// [`ProgramUsage::is_evictable`] while the cache is above 90% of
+        /// `MaxProgramCacheBytes` or once they are stale, releasing their storage deposits.
+        fn evict_programs(remaining_weight: Weight) -> Weight {
+            let db = T::DbWeight::get();
+            // Cursor and total read and write
+            let mut used = db.reads_writes(2, 2);
+            // Usage read, pin check over the message count and accepted key versions,
+            // removal and release of the storage deposit
+            let per_program = db.reads_writes(8, 5);
+
+            if !used.saturating_add(per_program).all_lte(remaining_weight) {
+                return Weight::zero();
+            }
+
+            let now = Self::current_block();
+            let max_age = T::MaxProgramAge::get() as u64;
+            // Evict early above 90% of the budget, leaving room for new programs
+            let budget = T::MaxProgramCacheBytes::get();
+            let threshold = budget.saturating_sub(budget / 10);
+            let mut total = ProgramCacheBytes::<T>::get();
+
+            let mut iter = match EvictionCursor::<T>::get() {
+                Some(cursor) => ProgramCacheUsage::<T>::iter_from(cursor.into_inner()),
+                None => ProgramCacheUsage::<T>::iter(),
+            };
+
+            let mut evicted = Vec::new();
+            let mut finished = false;
+            while used.saturating_add(per_program).all_lte(remaining_weight) {
+                match iter.next() {
+                    Some((program_hash, usage)) => {
+                        used = used.saturating_add(per_program);
+                        if usage.is_evictable(now, max_age, total > threshold)
+                            && !Self::is_program_pinned(&program_hash)
+                        {
+                            total = total.saturating_sub(usage.size as u64);
+                            evicted.push((program_hash, usage.use_count));
+                        }
+                    }
+                    None => {
+                        finished = true;
+                        break;
+                    }
+                }
+            }
+
+            if finished {
+                EvictionCursor::<T>::kill();
+            } else {
+                // A key longer than the bound only restarts the sweep
+                EvictionCursor::<T>::set(BoundedVec::try_from(iter.last_raw_key().to_vec()).ok());
+            }
+
+            for (program_hash, use_count) in evicted {
+                ProgramCache::<T>::remove(program_hash);
+                ProgramCacheUsage::<T>::remove(program_hash);
+                Self::deposit_event(Event::ProgramEvicted { program_hash, use_count });
+                if let Some((who, deposit)) = ProgramDeposits::<T>::take(program_hash) {
+                    let deposit_released = T::Currency::release(
+                        &HoldReason::ProgramStorage.into(),
+                        &who,
+                        deposit,
+                        Precision::BestEffort,
+                    )
+                    .unwrap_or_else(|_| Zero::zero());
+                    Self::deposit_event(Event::ProgramDepositReleased { program_hash, who, deposit_released });
+                }
+            }
+            ProgramCacheBytes::<T>::put(total);
+
+            used
+        }
+
+        /// Remove an upload and its chunks and release its deposit
+        ///
+        /// Returns the amount released.
+        fn remove_upload(upload_id: UploadId, upload: &UploadOf<T>) -> BalanceOf<T> {
+            let _ = UploadChunks::<T>::clear_prefix(upload_id, upload.chunks, None);
+            Uploads::<T>::remove(upload_id);
+            T::Currency::release(
+                &HoldReason::Upload.into(),
+                &upload.owner,
+                upload.deposit,
+                Precision::BestEffort,
+            )
+            .unwrap_or_else(|_| Zero::zero())
+        }
+
+        /// Chunks of the largest possible upload
+        fn max_upload_chunks() -> u32 {
+            let max_length = T::MaxProgramSize::get().max(T::MaxProofSize::get());
+            max_chunks(max_length, T::MaxUploadChunkSize::get())
+        }
+
+        /// Remove timed out uploads within the given weight budget
+        ///
+        /// Iteration resumes from `UploadCursor`, so all uploads are swept
+        /// incrementally across blocks.
+        fn expire_uploads(remaining_weight: Weight) -> Weight {
+            let db = T::DbWeight::get();
+            // Cursor read and write
+            let mut used = db.reads_writes(1, 1);
+            // Upload read, removal with all chunks and the hold release
+            let per_upload = db.reads_writes(2, Self::max_upload_chunks() as u64 + 2);
+
+            if !used.saturating_add(per_upload).all_lte(remaining_weight) {
+                return Weight::zero();
+            }
+
+            let now = Self::current_block();
+            let mut iter = match UploadCursor::<T>::get() {
+                Some(cursor) => Uploads::<T>::iter_from(cursor.into_inner()),
+                None => Uploads::<T>::iter(),
+            };
+
+            let mut expired = Vec::new();
+            let mut finished = false;
+            while used.saturating_add(per_upload).all_lte(remaining_weight) {
+                match iter.next() {
+                    Some((upload_id, upload)) => {
+                        used = used.saturating_add(per_upload);
+                        if upload.is_expired(now) {
+                            expired.push((upload_id, upload));
+                        }
+                    }
+                    None => {
+                        finished = true;
+                        break;
+                    }
+                }
+            }
+
+            if finished {
+                UploadCursor::<T>::kill();
+            } else {
+                // A key longer than the bound only restarts the sweep
+                UploadCursor::<T>::set(BoundedVec::try_from(iter.last_raw_key().to_vec()).ok());
+            }
+
+            for (upload_id, upload) in expired {
+                let deposit_released = Self::remove_upload(upload_id, &upload);
+                Self::deposit_event(Event::UploadRemoved { upload_id, expired: true, deposit_released });
+            }
+
+            used
+        }
+
+        /// Remove sender rate counters that have fully decayed, within `remaining_weight`
+        ///
+        /// Iteration resumes from `RateCounterCursor`, so all counters are
+        /// swept incrementally across blocks.
+        fn prune_rate_counters(remaining_weight: Weight) -> Weight {
+            let db = T::DbWeight::get();
+            // Cursor read and write
+            let mut used = db.reads_writes(1, 1);
+            // Counter read and removal
+            let per_counter = db.reads_writes(1, 1);
+
+            if !used.saturating_add(per_counter).all_lte(remaining_weight) {
+                return Weight::zero();
+            }
+
+            let now = Self::current_block();
+            let window = T::RateLimitWindow::get() as u64;
+            let mut iter = match RateCounterCursor::<T>::get() {
+                Some(cursor) => SenderRateCounters::<T>::iter_from(cursor.into_inner()),
+                None => SenderRateCounters::<T>::iter(),
+            };
+
+            let mut decayed = Vec::new();
+            let mut finished = false;
+            while used.saturating_add(per_counter).all_lte(remaining_weight) {
+                match iter.next() {
+                    Some((sender, counter)) => {
+                        used = used.saturating_add(per_counter);
+                        if counter.decayed(now, window) == 0 {
+                            decayed.push(sender);
+                        }
+                    }
+                    None => {
+                        finished = true;
+                        break;
+                    }
+                }
+            }
+
+            if finished {
+                RateCounterCursor::<T>::kill();
+            } else {
+                // A key longer than the bound only restarts the sweep
+                RateCounterCursor::<T>::set(BoundedVec::try_from(iter.last_raw_key().to_vec()).ok());
+            }
+
+            for sender in decayed {
+                SenderRateCounters::<T>::remove(sender);
+            }
+
+            used
+        }
+
+        /// Whether a program claim made at block `committed_at` has outlived `ClaimTimeout`
+        fn is_claim_expired(committed_at: u64) -> bool {
+            Self::current_block().saturating_sub(committed_at) > T::ClaimTimeout::get() as u64
+        }
+
+        /// Remove expired program claims and release their deposits, within `remaining_weight`
+        ///
+        /// Iteration resumes from `ClaimCursor`, so all claims are swept
+        /// incrementally across blocks.
+        fn expire_claims(remaining_weight: Weight) -> Weight {
+            let db = T::DbWeight::get();
+            // Cursor read and write
+            let mut used = db.reads_writes(1, 1);
+            // Claim read and removal with the hold release
+            let per_claim = db.reads_writes(3, 3);
+
+            if !used.saturating_add(per_claim).all_lte(remaining_weight) {
+                return Weight::zero();
+            }
+
+            let mut iter = match ClaimCursor::<T>::get() {
+                Some(cursor) => ProgramClaims::<T>::iter_from(cursor.into_inner()),
+                None => ProgramClaims::<T>::iter(),
+            };
+
+            let mut expired = Vec::new();
+            let mut finished = false;
+            while used.saturating_add(per_claim).all_lte(remaining_weight) {
+                match iter.next() {
+                    Some((who, (_, committed_at, deposit))) => {
+                        used = used.saturating_add(per_claim);
+                        if Self::is_claim_expired(committed_at) {
+                            expired.push((who, deposit));
+                        }
+                    }
+                    None => {
+                        finished = true;
+                        break;
+                    }
+                }
+            }
+
+            if finished {
+                ClaimCursor::<T>::kill();
+            } else {
+                // A key longer than the bound only restarts the sweep
+                ClaimCursor::<T>::set(BoundedVec::try_from(iter.last_raw_key().to_vec()).ok());
+            }
+
+            for (who, deposit) in expired {
+                ProgramClaims::<T>::remove(&who);
+                let deposit_released = T::Currency::release(
+                    &HoldReason::ProgramClaim.into(),
+                    &who,
+                    deposit,
+                    Precision::BestEffort,
+                )
+                .unwrap_or_else(|_| Zero::zero());
+                Self::deposit_event(Event::ProgramClaimExpired { who, deposit_released });
+            }
+
+            used
         }
     }
 } 
\ No newline at end of file
@@ -0,0 +1,211 @@
+//! Canonical message identifiers
+//!
+//! A message ID commits only to the immutable fields of a message, so it does
+//! not change when a proof is attached or the status moves on. The ID is
+//!
+//! ```text
+//! H(MESSAGE_ID_DOMAIN ++ from_chain ++ to_chain ++ lane ++ nonce ++ source_sender ++ program_hash ++ payload_hash)
+//! ```
+//!
+//! where `from_chain`, `to_chain` and `lane` are `u32`, `nonce` is `u64`,
+//! `source_sender` is the 32-byte sender address on the source chain (shorter
+//! addresses are left-padded with zeros), `program_hash` identifies the
+//! program whose proof attests to the message and `payload_hash` is
+//! `H(payload)`.
+//! The runtime picks the hash function and integer encoding through
+//! `Config::MessageHasher`:
+//!
+//! - [`Blake2Scale`]: `H` is blake2-256 and integers are little-endian, i.e.
+//!   the SCALE encoding of [`MessageIdFields`] prefixed with the domain tag.
+//! - [`KeccakAbiPacked`]: `H` is keccak-256 and integers are big-endian, i.e.
+//!   `keccak256(abi.encodePacked(domain, fromChain, toChain, lane, nonce,
+//!   sourceSender, programHash, payloadHash))` with `uint32`/`uint64`/`bytes32`
+//!   types, which EVM contracts can recompute.
+//!
+//! Proofs commit to the same fields through their public inputs, which are
+//! the preimage above, so under [`KeccakAbiPacked`] an EVM contract can also
+//! rebuild the public inputs of a message.
+//!
+//! Changing the hasher on a live chain changes every message ID.
+
+use sp_std::prelude::*;
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_core::H256;
+use sp_io::hashing::{blake2_256, keccak_256};
+use sp_runtime::RuntimeDebug;
+
+/// Domain separation tag prepended to every message ID preimage
+pub const MESSAGE_ID_DOMAIN: &[u8] = b"frostgate/message-id/v2";
+
+/// Canonical message identifier
+pub type MessageId = H256;
+
+/// Immutable message fields committed to by the message ID
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo)]
+pub struct MessageIdFields {
+    /// Source chain ID
+    pub from_chain: u32,
+    /// Destination chain ID
+    pub to_chain: u32,
+    /// Source chain lane
+    pub lane: u32,
+    /// Source chain nonce
+    pub nonce: u64,
+    /// Sender address on the source chain
+    pub source_sender: [u8; 32],
+    /// Program whose proof attests to the message
+    pub program_hash: [u8; 32],
+    /// Hash of the message payload
+    pub payload_hash: [u8; 32],
+}
+
+impl MessageIdFields {
+    /// Collect the ID fields of a message
+    pub fn new(
+        from_chain: u32,
+        to_chain: u32,
+        lane: u32,
+        nonce: u64,
+        source_sender: [u8; 32],
+        program_hash: [u8; 32],
+        payload_hash: [u8; 32],
+    ) -> Self {
+        Self {
+            from_chain,
+            to_chain,
+            lane,
+            nonce,
+            source_sender,
+            program_hash,
+            payload_hash,
+        }
+    }
+
+    /// Preimage with little-endian integers (SCALE)
+    pub fn scale_preimage(&self) -> Vec<u8> {
+        let mut preimage = MESSAGE_ID_DOMAIN.to_vec();
+        self.encode_to(&mut preimage);
+        preimage
+    }
+
+    /// Preimage with big-endian integers (Solidity `abi.encodePacked`)
+    pub fn abi_packed_preimage(&self) -> Vec<u8> {
+        let mut preimage = MESSAGE_ID_DOMAIN.to_vec();
+        preimage.extend_from_slice(&self.from_chain.to_be_bytes());
+        preimage.extend_from_slice(&self.to_chain.to_be_bytes());
+        preimage.extend_from_slice(&self.lane.to_be_bytes());
+        preimage.extend_from_slice(&self.nonce.to_be_bytes());
+        preimage.extend_from_slice(&self.source_sender);
+        preimage.extend_from_slice(&self.program_hash);
+        preimage.extend_from_slice(&self.payload_hash);
+        preimage
+    }
+}
+
+/// Scheme used to compute message IDs and payload commitments
+pub trait MessageHasher {
+    /// Hash of a message payload as committed to by the message ID
+    fn payload_hash(payload: &[u8]) -> [u8; 32];
+
+    /// Compute the message ID
+    fn message_id(fields: &MessageIdFields) -> MessageId;
+
+    /// Public inputs a message proof commits to, the message ID preimage
+    fn public_inputs(fields: &MessageIdFields) -> Vec<u8>;
+}
+
+/// Blake2-256 over the SCALE encoding
+pub struct Blake2Scale;
+
+impl MessageHasher for Blake2Scale {
+    fn payload_hash(payload: &[u8]) -> [u8; 32] {
+        blake2_256(payload)
+    }
+
+    fn message_id(fields: &MessageIdFields) -> MessageId {
+        H256(blake2_256(&fields.scale_preimage()))
+    }
+
+    fn public_inputs(fields: &MessageIdFields) -> Vec<u8> {
+        fields.scale_preimage()
+    }
+}
+
+/// Keccak-256 over the ABI packed encoding, reproducible on EVM chains
+pub struct KeccakAbiPacked;
+
+impl MessageHasher for KeccakAbiPacked {
+    fn payload_hash(payload: &[u8]) -> [u8; 32] {
+        keccak_256(payload)
+    }
+
+    fn message_id(fields: &MessageIdFields) -> MessageId {
+        H256(keccak_256(&fields.abi_packed_preimage()))
+    }
+
+    fn public_inputs(fields: &MessageIdFields) -> Vec<u8> {
+        fields.abi_packed_preimage()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_preimage_layout() {
+        let fields = MessageIdFields::new(1, 2, 3, 4, [5; 32], [7; 32], [6; 32]);
+
+        let mut expected = MESSAGE_ID_DOMAIN.to_vec();
+        expected.extend_from_slice(&1u32.to_le_bytes());
+        expected.extend_from_slice(&2u32.to_le_bytes());
+        expected.extend_from_slice(&3u32.to_le_bytes());
+        expected.extend_from_slice(&4u64.to_le_bytes());
+        expected.extend_from_slice(&[5; 32]);
+        expected.extend_from_slice(&[7; 32]);
+        expected.extend_from_slice(&[6; 32]);
+        assert_eq!(fields.scale_preimage(), expected);
+    }
+
+    #[test]
+    fn test_abi_packed_preimage_layout() {
+        let fields = MessageIdFields::new(1, 2, 3, 4, [5; 32], [7; 32], [6; 32]);
+
+        let mut expected = MESSAGE_ID_DOMAIN.to_vec();
+        expected.extend_from_slice(&[0, 0, 0, 1]);
+        expected.extend_from_slice(&[0, 0, 0, 2]);
+        expected.extend_from_slice(&[0, 0, 0, 3]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 4]);
+        expected.extend_from_slice(&[5; 32]);
+        expected.extend_from_slice(&[7; 32]);
+        expected.extend_from_slice(&[6; 32]);
+        assert_eq!(fields.abi_packed_preimage(), expected);
+        assert_eq!(
+            KeccakAbiPacked::message_id(&fields),
+            H256(keccak_256(&expected)),
+        );
+    }
+
+    #[test]
+    fn test_message_id_depends_on_every_field() {
+        let id = |fields| Blake2Scale::message_id(&fields);
+        let base = id(MessageIdFields::new(1, 2, 3, 4, [5; 32], [7; 32], [6; 32]));
+
+        assert_eq!(base, id(MessageIdFields::new(1, 2, 3, 4, [5; 32], [7; 32], [6; 32])));
+        assert_ne!(base, id(MessageIdFields::new(0, 2, 3, 4, [5; 32], [7; 32], [6; 32])));
+        assert_ne!(base, id(MessageIdFields::new(1, 0, 3, 4, [5; 32], [7; 32], [6; 32])));
+        assert_ne!(base, id(MessageIdFields::new(1, 2, 0, 4, [5; 32], [7; 32], [6; 32])));
+        assert_ne!(base, id(MessageIdFields::new(1, 2, 3, 0, [5; 32], [7; 32], [6; 32])));
+        assert_ne!(base, id(MessageIdFields::new(1, 2, 3, 4, [0; 32], [7; 32], [6; 32])));
+        assert_ne!(base, id(MessageIdFields::new(1, 2, 3, 4, [5; 32], [0; 32], [6; 32])));
+        assert_ne!(base, id(MessageIdFields::new(1, 2, 3, 4, [5; 32], [7; 32], [0; 32])));
+    }
+
+    #[test]
+    fn test_hashers_disagree() {
+        let fields = MessageIdFields::new(1, 2, 3, 4, [5; 32], [7; 32], [6; 32]);
+        assert_ne!(Blake2Scale::message_id(&fields), KeccakAbiPacked::message_id(&fields));
+        assert_ne!(Blake2Scale::payload_hash(&[1]), KeccakAbiPacked::payload_hash(&[1]));
+    }
+}
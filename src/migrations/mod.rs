@@ -0,0 +1,6 @@
+//! Storage migrations for the Frostgate verifier pallet
+
+/// Chain ID encoding before the chain registry, the index of a fixed enum
+pub(crate) type LegacyChainId = u8;
+
+pub mod v1;
@@ -0,0 +1,439 @@
+//! Migration from the initial layout to v1
+//!
+//! The initial pallet stored messages keyed by their hash with a deposit
+//! reserved from the sender, per-sender nonces, one opaque verification key
+//! per program and unbounded cached programs. Every storage item is read and
+//! written through the layouts declared in this module, so later changes to
+//! the pallet's types do not change what this migration does.
+//!
+//! - Messages are removed and their reserved deposit returned. They name no
+//!   program, source sender or lane, so no proof could verify them under v1.
+//! - Sender nonces are removed; v1 tracks inbound nonces per lane.
+//! - Verification keys become version 1 of their program's key once prepared
+//!   by `Config::KeyPreparer`. Keys that do not derive their program hash,
+//!   exceed the size bounds or are rejected by the backend are removed.
+//!   Opaque metadata that decodes as a metadata record is kept.
+//! - Cached programs are kept if they fit the size bounds, hash to the
+//!   program hash they are stored under and fit `MaxProgramCacheBytes`.
+//!
+//! No chains or routes are registered: the initial pallet had none, and
+//! messages are rejected until governance configures them.
+//!
+//! At most `MaxEntries` entries of each storage item are processed. Runtimes
+//! whose state holds more must raise it; `pre_upgrade` fails in that case.
+
+use sp_std::{marker::PhantomData, prelude::*};
+use codec::{Decode, Encode};
+use frame_support::{
+    pallet_prelude::*,
+    storage_alias,
+    traits::{OnRuntimeUpgrade, ReservableCurrency},
+};
+use sp_runtime::traits::Zero;
+use crate::{
+    migrations::LegacyChainId,
+    pallet::{BalanceOf, Config, Pallet},
+    verification::{KeyPreparer, ProgramHasher},
+    LOG_TARGET,
+};
+
+/// Storage items in their initial layout
+mod v0 {
+    use super::*;
+
+    /// Message status in the initial layout
+    #[derive(Decode)]
+    pub enum MessageStatus {
+        Pending,
+        Verified,
+        Failed,
+    }
+
+    /// Message in the initial layout
+    #[derive(Decode)]
+    #[allow(dead_code)]
+    pub struct Message<AccountId> {
+        pub from_chain: LegacyChainId,
+        pub to_chain: LegacyChainId,
+        pub sender: AccountId,
+        pub payload: Vec<u8>,
+        pub nonce: u64,
+        pub timestamp: u64,
+        pub status: MessageStatus,
+        pub proof: Option<Vec<u8>>,
+    }
+
+    /// Verification key in the initial layout
+    #[derive(Decode)]
+    pub struct VerificationKeyEntry {
+        pub program_hash: [u8; 32],
+        pub key_bytes: Vec<u8>,
+        pub added_at: u64,
+        pub metadata: Option<Vec<u8>>,
+    }
+
+    /// Cached program in the initial layout
+    #[derive(Decode)]
+    pub struct ProgramCacheEntry {
+        pub hash: [u8; 32],
+        pub bytes: Vec<u8>,
+        pub cached_at: u64,
+        pub use_count: u64,
+    }
+
+    #[storage_alias]
+    pub type Messages<T: Config> = StorageMap<
+        Pallet<T>,
+        Blake2_128Concat,
+        <T as frame_system::Config>::Hash,
+        Message<<T as frame_system::Config>::AccountId>,
+        OptionQuery,
+    >;
+
+    #[storage_alias]
+    pub type Nonces<T: Config> = StorageDoubleMap<
+        Pallet<T>,
+        Blake2_128Concat,
+        LegacyChainId,
+        Blake2_128Concat,
+        <T as frame_system::Config>::AccountId,
+        u64,
+        ValueQuery,
+    >;
+
+    #[storage_alias]
+    pub type VerificationKeys<T: Config> = StorageMap<
+        Pallet<T>,
+        Blake2_128Concat,
+        [u8; 32],
+        VerificationKeyEntry,
+        OptionQuery,
+    >;
+
+    #[storage_alias]
+    pub type ProgramCache<T: Config> = StorageMap<
+        Pallet<T>,
+        Blake2_128Concat,
+        [u8; 32],
+        ProgramCacheEntry,
+        OptionQuery,
+    >;
+}
+
+/// Storage items in their v1 layout
+mod v1 {
+    use super::*;
+
+    /// Key status in v1
+    #[derive(Encode, Decode)]
+    pub enum KeyStatus<MaxReasonLen: Get<u32>> {
+        Active,
+        Frozen { reason: BoundedVec<u8, MaxReasonLen>, since: u64 },
+        Revoked { reason: BoundedVec<u8, MaxReasonLen>, since: u64 },
+    }
+
+    /// Key metadata in v1
+    #[derive(Encode, Decode)]
+    pub struct KeyMetadata<AccountId, MaxLen: Get<u32>> {
+        pub circuit_name: BoundedVec<u8, MaxLen>,
+        pub circuit_version: BoundedVec<u8, MaxLen>,
+        pub source_commit: BoundedVec<u8, MaxLen>,
+        pub public_input_layout: BoundedVec<u8, MaxLen>,
+        pub setup_transcript_hash: Option<[u8; 32]>,
+        pub owner: Option<AccountId>,
+    }
+
+    /// Verification key in v1
+    #[derive(Encode, Decode)]
+    pub struct VerificationKeyEntry<
+        AccountId,
+        MaxKeySize: Get<u32>,
+        MaxPreparedKeySize: Get<u32>,
+        MaxMetadataSize: Get<u32>,
+        MaxReasonLen: Get<u32>,
+    > {
+        pub program_hash: [u8; 32],
+        pub key_bytes: BoundedVec<u8, MaxKeySize>,
+        pub prepared_key: BoundedVec<u8, MaxPreparedKeySize>,
+        pub added_at: u64,
+        pub added_by: Option<AccountId>,
+        pub metadata: Option<KeyMetadata<AccountId, MaxMetadataSize>>,
+        pub status: KeyStatus<MaxReasonLen>,
+    }
+
+    /// Verification key type as stored by a given runtime in v1
+    pub type VerificationKeyEntryOf<T> = VerificationKeyEntry<
+        <T as frame_system::Config>::AccountId,
+        <T as Config>::MaxKeySize,
+        <T as Config>::MaxPreparedKeySize,
+        <T as Config>::MaxMetadataSize,
+        <T as Config>::MaxReasonLen,
+    >;
+
+    /// Accepted key versions in v1
+    #[derive(Encode, Decode)]
+    pub struct KeyVersionInfo {
+        pub active: u32,
+        pub previous: Option<u32>,
+        pub previous_valid_until: u64,
+    }
+
+    /// Cached program in v1
+    #[derive(Encode, Decode)]
+    pub struct ProgramCacheEntry<MaxProgramSize: Get<u32>> {
+        pub hash: [u8; 32],
+        pub bytes: BoundedVec<u8, MaxProgramSize>,
+        pub cached_at: u64,
+    }
+
+    /// Usage of a cached program in v1
+    #[derive(Encode, Decode)]
+    pub struct ProgramUsage {
+        pub size: u32,
+        pub use_count: u64,
+        pub last_used: u64,
+    }
+
+    #[storage_alias]
+    pub type VerificationKeys<T: Config> = StorageDoubleMap<
+        Pallet<T>,
+        Blake2_128Concat,
+        [u8; 32],
+        Twox64Concat,
+        u32,
+        VerificationKeyEntryOf<T>,
+        OptionQuery,
+    >;
+
+    #[storage_alias]
+    pub type ActiveKeyVersions<T: Config> = StorageMap<
+        Pallet<T>,
+        Blake2_128Concat,
+        [u8; 32],
+        KeyVersionInfo,
+        OptionQuery,
+    >;
+
+    #[storage_alias]
+    pub type ProgramCache<T: Config> = StorageMap<
+        Pallet<T>,
+        Blake2_128Concat,
+        [u8; 32],
+        ProgramCacheEntry<<T as Config>::MaxProgramSize>,
+        OptionQuery,
+    >;
+
+    #[storage_alias]
+    pub type ProgramCacheUsage<T: Config> =
+        StorageMap<Pallet<T>, Blake2_128Concat, [u8; 32], ProgramUsage, OptionQuery>;
+
+    #[storage_alias]
+    pub type ProgramCacheBytes<T: Config> = StorageValue<Pallet<T>, u64, ValueQuery>;
+}
+
+/// Version a migrated key is stored under
+const MIGRATED_KEY_VERSION: u32 = 1;
+
+/// Migrates the initial storage layout to v1
+///
+/// `OldCurrency` must be the `ReservableCurrency` the pallet was configured
+/// with before the upgrade. `MaxEntries` bounds the entries processed per
+/// storage item.
+pub struct MigrateToV1<T, OldCurrency, MaxEntries>(PhantomData<(T, OldCurrency, MaxEntries)>);
+
+impl<T, OldCurrency, MaxEntries> MigrateToV1<T, OldCurrency, MaxEntries>
+where
+    T: Config,
+    OldCurrency: ReservableCurrency<T::AccountId, Balance = BalanceOf<T>>,
+    MaxEntries: Get<u32>,
+{
+    /// Remove messages and return their reserved deposits
+    fn remove_messages() -> (u64, Weight) {
+        let deposit = T::MessageDeposit::get();
+        let mut removed = 0u64;
+        // Nothing is inserted under the prefix, so the entries can be drained as they are read
+        for message in v0::Messages::<T>::drain().take(MaxEntries::get() as usize) {
+            removed += 1;
+            let remaining = OldCurrency::unreserve(&message.sender, deposit);
+            if !remaining.is_zero() {
+                log::warn!(target: LOG_TARGET, "{:?} of a message deposit was no longer reserved", remaining);
+            }
+        }
+        (removed, T::DbWeight::get().reads_writes(removed * 2, removed * 2))
+    }
+
+    /// Remove per-sender nonces
+    fn remove_nonces() -> (u64, Weight) {
+        let result = v0::Nonces::<T>::clear(MaxEntries::get(), None);
+        let removed = result.unique as u64;
+        (removed, T::DbWeight::get().reads_writes(result.loops as u64, removed))
+    }
+
+    /// Store keys as version 1 of their program's key, `(migrated, removed)`
+    fn migrate_keys() -> (u64, u64, Weight) {
+        // Old and new keys share a prefix, so drain before re-inserting
+        let keys: Vec<_> = v0::VerificationKeys::<T>::drain().take(MaxEntries::get() as usize).collect();
+        let mut migrated = 0u64;
+        for (program_hash, old) in &keys {
+            let Some(entry) = Self::migrate_key(program_hash, old) else {
+                log::warn!(target: LOG_TARGET, "removing verification key of program {:?}", program_hash);
+                continue;
+            };
+            v1::VerificationKeys::<T>::insert(program_hash, MIGRATED_KEY_VERSION, entry);
+            v1::ActiveKeyVersions::<T>::insert(program_hash, v1::KeyVersionInfo {
+                active: MIGRATED_KEY_VERSION,
+                previous: None,
+                previous_valid_until: 0,
+            });
+            migrated += 1;
+        }
+        let total = keys.len() as u64;
+        (migrated, total - migrated, T::DbWeight::get().reads_writes(total, total + migrated * 2))
+    }
+
+    /// Key in its v1 layout, `None` if it cannot be used under v1
+    fn migrate_key(program_hash: &[u8; 32], old: &v0::VerificationKeyEntry) -> Option<v1::VerificationKeyEntryOf<T>> {
+        if old.program_hash != *program_hash
+            || T::ProgramHasher::key_program_hash(&old.key_bytes) != Some(*program_hash)
+        {
+            return None;
+        }
+        let prepared_key = T::KeyPreparer::prepare(&old.key_bytes).ok()?.try_into().ok()?;
+        let metadata = old.metadata.as_ref().and_then(|bytes| {
+            let decoded = v1::KeyMetadata::decode(&mut &bytes[..]).ok();
+            if decoded.is_none() {
+                log::warn!(target: LOG_TARGET, "dropping metadata of key {:?}: not a metadata record", program_hash);
+            }
+            decoded
+        });
+
+        Some(v1::VerificationKeyEntry {
+            program_hash: *program_hash,
+            key_bytes: old.key_bytes.clone().try_into().ok()?,
+            prepared_key,
+            added_at: old.added_at,
+            added_by: None,
+            metadata,
+            status: v1::KeyStatus::Active,
+        })
+    }
+
+    /// Keep cached programs that are valid under v1, `(kept, removed)`
+    fn migrate_programs() -> (u64, u64, Weight) {
+        // Values are re-encoded under the same keys, so drain before re-inserting
+        let programs: Vec<_> = v0::ProgramCache::<T>::drain().take(MaxEntries::get() as usize).collect();
+        let budget = T::MaxProgramCacheBytes::get();
+        let mut total_bytes = 0u64;
+        let mut kept = 0u64;
+        for (hash, old) in programs.iter() {
+            let size = old.bytes.len() as u64;
+            let bytes = (old.hash == *hash && T::ProgramHasher::program_hash(&old.bytes) == *hash)
+                .then(|| BoundedVec::try_from(old.bytes.clone()).ok())
+                .flatten()
+                .filter(|_| total_bytes.saturating_add(size) <= budget);
+            let Some(bytes) = bytes else {
+                log::warn!(target: LOG_TARGET, "removing cached program {:?}", hash);
+                continue;
+            };
+            v1::ProgramCache::<T>::insert(hash, v1::ProgramCacheEntry {
+                hash: *hash,
+                bytes,
+                cached_at: old.cached_at,
+            });
+            v1::ProgramCacheUsage::<T>::insert(hash, v1::ProgramUsage {
+                size: size as u32,
+                use_count: old.use_count,
+                last_used: old.cached_at,
+            });
+            total_bytes = total_bytes.saturating_add(size);
+            kept += 1;
+        }
+        v1::ProgramCacheBytes::<T>::put(total_bytes);
+        let total = programs.len() as u64;
+        (kept, total - kept, T::DbWeight::get().reads_writes(total, total + 2 * kept + 1))
+    }
+}
+
+impl<T, OldCurrency, MaxEntries> OnRuntimeUpgrade for MigrateToV1<T, OldCurrency, MaxEntries>
+where
+    T: Config,
+    OldCurrency: ReservableCurrency<T::AccountId, Balance = BalanceOf<T>>,
+    MaxEntries: Get<u32>,
+{
+    fn on_runtime_upgrade() -> Weight {
+        let on_chain = Pallet::<T>::on_chain_storage_version();
+        if on_chain != 0 {
+            log::info!(target: LOG_TARGET, "skipping v1 migration, on-chain version is {:?}", on_chain);
+            return T::DbWeight::get().reads(1);
+        }
+
+        let (messages, messages_weight) = Self::remove_messages();
+        let (nonces, nonces_weight) = Self::remove_nonces();
+        let (keys, removed_keys, keys_weight) = Self::migrate_keys();
+        let (programs, removed_programs, programs_weight) = Self::migrate_programs();
+
+        if v0::Messages::<T>::iter_keys().next().is_some()
+            || v0::Nonces::<T>::iter_keys().next().is_some()
+        {
+            log::error!(target: LOG_TARGET, "v1 migration left entries behind, MaxEntries is too low");
+        }
+
+        StorageVersion::new(1).put::<Pallet<T>>();
+        log::info!(
+            target: LOG_TARGET,
+            "removed {} messages and {} nonces, migrated {} keys ({} removed) and {} programs ({} removed)",
+            messages,
+            nonces,
+            keys,
+            removed_keys,
+            programs,
+            removed_programs,
+        );
+
+        messages_weight
+            .saturating_add(nonces_weight)
+            .saturating_add(keys_weight)
+            .saturating_add(programs_weight)
+            .saturating_add(T::DbWeight::get().reads_writes(3, 1))
+    }
+
+    #[cfg(feature = "try-runtime")]
+    fn pre_upgrade() -> Result<Vec<u8>, sp_runtime::TryRuntimeError> {
+        let max = MaxEntries::get() as usize;
+        let keys = v0::VerificationKeys::<T>::iter_keys().count();
+        ensure!(v0::Messages::<T>::iter_keys().count() <= max, "more messages than MaxEntries");
+        ensure!(v0::Nonces::<T>::iter_keys().count() <= max, "more nonces than MaxEntries");
+        ensure!(keys <= max, "more verification keys than MaxEntries");
+        ensure!(v0::ProgramCache::<T>::iter_keys().count() <= max, "more cached programs than MaxEntries");
+        Ok((keys as u32).encode())
+    }
+
+    #[cfg(feature = "try-runtime")]
+    fn post_upgrade(state: Vec<u8>) -> Result<(), sp_runtime::TryRuntimeError> {
+        ensure!(
+            Pallet::<T>::on_chain_storage_version() == 1,
+            "v1 migration did not bump the storage version"
+        );
+        ensure!(v0::Messages::<T>::iter_keys().next().is_none(), "messages left behind");
+        ensure!(v0::Nonces::<T>::iter_keys().next().is_none(), "nonces left behind");
+
+        let old_keys = u32::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+        let mut keys = 0u32;
+        for (program_hash, version, _) in v1::VerificationKeys::<T>::iter() {
+            ensure!(version == MIGRATED_KEY_VERSION, "key stored under an unexpected version");
+            ensure!(v1::ActiveKeyVersions::<T>::contains_key(program_hash), "key without an active version");
+            keys += 1;
+        }
+        ensure!(keys <= old_keys, "more keys than before the migration");
+
+        let mut total = 0u64;
+        for (hash, entry) in v1::ProgramCache::<T>::iter() {
+            let usage = v1::ProgramCacheUsage::<T>::get(hash).ok_or("cached program without usage")?;
+            ensure!(usage.size as usize == entry.bytes.len(), "program usage size mismatch");
+            total = total.saturating_add(entry.bytes.len() as u64);
+        }
+        ensure!(v1::ProgramCacheBytes::<T>::get() == total, "program cache size mismatch");
+        ensure!(total <= T::MaxProgramCacheBytes::get(), "program cache over budget");
+        Ok(())
+    }
+}
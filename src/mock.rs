@@ -0,0 +1,265 @@
+//! Test runtime for the Frostgate verifier pallet
+
+use crate as pallet_frostgate_verifier;
+use crate::{
+    message_id::Blake2Scale,
+    pallet::{ChainId, KeyPolicy, RouteConfig, RouteConfigOf, VerificationMode},
+    rate_limit::PayloadValue,
+    sp1_keys::{Sp1Groth16Key, Sp1Groth16Verifier},
+    verification::{Blake2Digest, KeyPreparer, ProofVerifier, RawKey, VerificationError, VerificationResult},
+};
+use frame_support::{
+    parameter_types,
+    traits::{ConstU32, ConstU64, Everything, GenesisBuild},
+    weights::constants::RocksDbWeight,
+    PalletId,
+};
+use frame_system::EnsureRoot;
+use sp_core::H256;
+use sp_runtime::{
+    testing::{Header, TestSignature, UintAuthorityId},
+    traits::{BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system,
+        Balances: pallet_balances,
+        Verifier: pallet_frostgate_verifier,
+    }
+);
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = RocksDbWeight;
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ();
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+    type MaxLocks = ();
+    type MaxReserves = ();
+    type ReserveIdentifier = [u8; 8];
+    type Balance = u64;
+    type RuntimeEvent = RuntimeEvent;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU64<1>;
+    type AccountStore = System;
+    type WeightInfo = ();
+    type FreezeIdentifier = ();
+    type MaxFreezes = ();
+    type RuntimeHoldReason = RuntimeHoldReason;
+    type MaxHolds = ConstU32<8>;
+}
+
+/// Treats the first payload byte of messages from Ethereum as the transferred value
+pub struct FirstByteValue;
+
+impl PayloadValue for FirstByteValue {
+    fn transfer_value(from_chain: u32, payload: &[u8]) -> Option<u128> {
+        (from_chain == ETHEREUM).then(|| payload.first().copied().unwrap_or_default().into())
+    }
+}
+
+parameter_types! {
+    pub const VerifierPalletId: PalletId = PalletId(*b"fg/verif");
+    /// Prepare keys and verify proofs with the SP1 Groth16 backend
+    pub static Sp1Backend: bool = false;
+}
+
+impl pallet_frostgate_verifier::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type RuntimeHoldReason = RuntimeHoldReason;
+    type Currency = Balances;
+    type MaxPayloadSize = ConstU32<256>;
+    type MessageDeposit = ConstU64<10>;
+    type MaxKeySize = ConstU32<1024>;
+    type MaxPreparedKeySize = ConstU32<1024>;
+    type MaxProofSize = ConstU32<256>;
+    type MaxMetadataSize = ConstU32<64>;
+    type MaxProgramSize = ConstU32<1024>;
+    type MaxProgramAge = ConstU32<100>;
+    type MaxProgramCacheBytes = ConstU64<4096>;
+    type KeyActivationDelay = ConstU32<10>;
+    type KeyGracePeriod = ConstU32<20>;
+    type PalletId = VerifierPalletId;
+    type RelayerReward = ConstU64<0>;
+    type MessageTtl = ConstU32<50>;
+    type MaxProofAttempts = ConstU32<3>;
+    type ForceOrigin = EnsureRoot<u64>;
+    type KeyAdminOrigin = EnsureRoot<u64>;
+    type EmergencyKeyOrigin = EnsureRoot<u64>;
+    type ProgramAdminOrigin = EnsureRoot<u64>;
+    type KeyDeposit = ConstU64<50>;
+    type OwnerSignature = TestSignature;
+    type OwnerSigner = UintAuthorityId;
+    type MessageHasher = Blake2Scale;
+    type ProgramHasher = Blake2Digest;
+    type KeyPreparer = TestKeyPreparer;
+    type ProofVerifier = TestProofs;
+    type MaxChainNameLen = ConstU32<16>;
+    type MaxAllowedPrograms = ConstU32<4>;
+    type RouteAdminOrigin = EnsureRoot<u64>;
+    type ChainAdminOrigin = EnsureRoot<u64>;
+    type PauseOrigin = EnsureRoot<u64>;
+    type MaxReasonLen = ConstU32<32>;
+    type MaxRevokedMessageBatch = ConstU32<8>;
+    type MaxMessagesPerBlock = ConstU32<10>;
+    type RateLimitWindow = ConstU32<10>;
+    type MaxMessagesPerSenderWindow = ConstU32<5>;
+    type PayloadValue = FirstByteValue;
+    type MaxUploadChunkSize = ConstU32<64>;
+    type UploadTimeout = ConstU32<20>;
+    type UploadDepositBase = ConstU64<20>;
+    type UploadDepositPerByte = ConstU64<1>;
+    type ClaimDeposit = ConstU64<5>;
+    type ClaimTimeout = ConstU32<20>;
+    type WeightInfo = ();
+    #[cfg(feature = "runtime-benchmarks")]
+    type BenchmarkHelper = TestKeys;
+}
+
+/// Proofs that are the digest of the prepared key and the public inputs, or
+/// SP1 Groth16 proofs while [`Sp1Backend`] is set
+pub struct TestProofs;
+
+impl TestProofs {
+    /// Proof of `public_inputs` under `prepared_key`
+    pub fn prove(prepared_key: &[u8], public_inputs: &[u8]) -> Vec<u8> {
+        sp_io::hashing::blake2_256(&[prepared_key, public_inputs].concat()).to_vec()
+    }
+}
+
+impl ProofVerifier for TestProofs {
+    fn requires_program() -> bool {
+        !Sp1Backend::get()
+    }
+
+    fn verify(
+        program: &[u8],
+        program_hash: &[u8; 32],
+        prepared_key: &[u8],
+        proof: &[u8],
+        public_inputs: &[u8],
+    ) -> VerificationResult {
+        if Sp1Backend::get() {
+            return Sp1Groth16Verifier::verify(program, program_hash, prepared_key, proof, public_inputs);
+        }
+        if proof != Self::prove(prepared_key, public_inputs) {
+            return Err(VerificationError::VerificationFailed);
+        }
+        Ok(())
+    }
+}
+
+/// Raw keys, or SP1 Groth16 keys while [`Sp1Backend`] is set
+pub struct TestKeyPreparer;
+
+impl KeyPreparer for TestKeyPreparer {
+    fn prepare(key_bytes: &[u8]) -> Result<Vec<u8>, VerificationError> {
+        if Sp1Backend::get() {
+            return Sp1Groth16Key::prepare(key_bytes);
+        }
+        RawKey::prepare(key_bytes)
+    }
+}
+
+/// Program bytes and a key accepted by the test key checks
+pub struct TestKeys;
+
+impl TestKeys {
+    /// Program bytes claimed and cached in tests
+    pub fn program() -> Vec<u8> {
+        b"test program".to_vec()
+    }
+
+    /// Verification key for [`TestKeys::program`], see [`Blake2Digest`]
+    pub fn key() -> Vec<u8> {
+        [&Self::program_hash()[..], b"test key"].concat()
+    }
+
+    /// Program hash of [`TestKeys::program`]
+    pub fn program_hash() -> [u8; 32] {
+        sp_io::hashing::blake2_256(&Self::program())
+    }
+}
+
+#[cfg(feature = "runtime-benchmarks")]
+impl crate::keys::BenchmarkHelper for TestKeys {
+    fn program_and_key() -> (Vec<u8>, Vec<u8>) {
+        (Self::program(), Self::key())
+    }
+}
+
+pub const ALICE: u64 = 1;
+pub const BOB: u64 = 2;
+pub const CHARLIE: u64 = 3;
+
+/// Source chain of test messages
+pub const ETHEREUM: ChainId = 1;
+/// Destination chain of test messages
+pub const POLKADOT: ChainId = 2;
+
+/// Program allowed on the test route
+pub const PROGRAM: [u8; 32] = [7; 32];
+
+/// Free balance of each test account
+pub const INITIAL_BALANCE: u64 = 1_000;
+
+/// Route configuration accepting proofs of `programs`
+pub fn route(programs: &[[u8; 32]]) -> RouteConfigOf<Test> {
+    RouteConfig {
+        deposit: 10,
+        max_payload_size: 256,
+        allowed_programs: programs.to_vec().try_into().unwrap(),
+        verification_mode: VerificationMode::Sp1,
+        key_policy: KeyPolicy::GovernanceOnly,
+    }
+}
+
+/// Externalities with funded accounts and Ethereum and Polkadot registered
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![(ALICE, INITIAL_BALANCE), (BOB, INITIAL_BALANCE), (CHARLIE, INITIAL_BALANCE)],
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+    pallet_frostgate_verifier::GenesisConfig::<Test> {
+        chains: vec![(ETHEREUM, b"Ethereum".to_vec(), 256), (POLKADOT, b"Polkadot".to_vec(), 256)],
+        ..Default::default()
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
@@ -0,0 +1,89 @@
+//! Rate limiting primitives
+//!
+//! Limits are tracked with [`RateCounter`]s whose usage decays linearly to
+//! zero over a window of blocks, so capacity frees up gradually rather than
+//! all at once at a window boundary.
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sp_runtime::{Perquintill, RuntimeDebug};
+
+/// Usage counter that decays linearly over a window of blocks
+#[derive(Clone, Copy, PartialEq, Eq, Default, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct RateCounter {
+    /// Usage recorded at `updated_at`
+    pub used: u128,
+    /// Block number of the last update
+    pub updated_at: u64,
+}
+
+impl RateCounter {
+    /// Usage remaining at block `now` after decay
+    pub fn decayed(&self, now: u64, window: u64) -> u128 {
+        let elapsed = now.saturating_sub(self.updated_at);
+        if elapsed >= window {
+            return 0;
+        }
+        self.used.saturating_sub(Perquintill::from_rational(elapsed, window) * self.used)
+    }
+
+    /// Record `amount` of usage at block `now`
+    ///
+    /// Returns the updated counter, or `None` if the decayed usage plus
+    /// `amount` would exceed `limit`.
+    pub fn try_add(&self, amount: u128, limit: u128, now: u64, window: u64) -> Option<Self> {
+        let used = self.decayed(now, window).checked_add(amount)?;
+        (used <= limit).then_some(Self { used, updated_at: now })
+    }
+}
+
+/// Extracts the transferred value from a message payload
+///
+/// Used to apply per-chain volume caps to token transfer messages. Payloads
+/// that are not transfers return `None` and are not counted.
+pub trait PayloadValue {
+    /// Value transferred by a message from `from_chain`
+    fn transfer_value(from_chain: u32, payload: &[u8]) -> Option<u128>;
+}
+
+impl PayloadValue for () {
+    fn transfer_value(_from_chain: u32, _payload: &[u8]) -> Option<u128> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_decays_linearly() {
+        let counter = RateCounter { used: 100, updated_at: 10 };
+
+        assert_eq!(counter.decayed(10, 10), 100);
+        assert_eq!(counter.decayed(15, 10), 50);
+        assert_eq!(counter.decayed(20, 10), 0);
+        assert_eq!(counter.decayed(5, 10), 100);
+    }
+
+    #[test]
+    fn test_counter_enforces_limit() {
+        let counter = RateCounter::default();
+
+        let counter = counter.try_add(3, 5, 1, 10).unwrap();
+        let counter = counter.try_add(2, 5, 1, 10).unwrap();
+        assert_eq!(counter, RateCounter { used: 5, updated_at: 1 });
+        assert!(counter.try_add(1, 5, 1, 10).is_none());
+
+        // Half the window frees half the capacity
+        assert!(counter.try_add(3, 5, 6, 10).is_none());
+        assert!(counter.try_add(2, 5, 6, 10).is_some());
+    }
+
+    #[test]
+    fn test_zero_window_does_not_limit_across_blocks() {
+        let counter = RateCounter { used: 5, updated_at: 1 };
+        assert_eq!(counter.decayed(1, 0), 0);
+        assert!(counter.try_add(5, 5, 2, 0).is_some());
+    }
+}
@@ -0,0 +1,39 @@
+//! Runtime API for inspecting the keys the bridge trusts
+//!
+//! The runtime implements it by delegating to the pallet, e.g.
+//!
+//! ```ignore
+//! impl pallet_frostgate_verifier::runtime_api::VerificationKeyApi<Block, VerificationKeyEntryOf<Runtime>>
+//!     for Runtime
+//! {
+//!     fn verification_keys(program_hash: [u8; 32]) -> Vec<(KeyVersion, VerificationKeyEntryOf<Runtime>)> {
+//!         FrostgateVerifier::verification_keys(program_hash)
+//!     }
+//!
+//!     fn accepted_key_versions(program_hash: [u8; 32]) -> Vec<KeyVersion> {
+//!         FrostgateVerifier::accepted_key_versions(&program_hash)
+//!     }
+//!
+//!     fn all_verification_keys() -> Vec<([u8; 32], KeyVersion, VerificationKeyEntryOf<Runtime>)> {
+//!         FrostgateVerifier::all_verification_keys()
+//!     }
+//! }
+//! ```
+
+use sp_std::prelude::*;
+use codec::Codec;
+use crate::keys::KeyVersion;
+
+sp_api::decl_runtime_apis! {
+    /// Verification keys and their metadata
+    pub trait VerificationKeyApi<KeyEntry> where KeyEntry: Codec {
+        /// All stored versions of a program's verification key, with status and metadata
+        fn verification_keys(program_hash: [u8; 32]) -> Vec<(KeyVersion, KeyEntry)>;
+
+        /// Key versions currently accepted for a program, active first
+        fn accepted_key_versions(program_hash: [u8; 32]) -> Vec<KeyVersion>;
+
+        /// Every stored key of every program, for auditing all keys the bridge trusts
+        fn all_verification_keys() -> Vec<([u8; 32], KeyVersion, KeyEntry)>;
+    }
+}
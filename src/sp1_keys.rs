@@ -0,0 +1,459 @@
+//! SP1 verifying key parsing and proof verification
+//!
+//! SP1 wraps the proof of every program in a Groth16 proof over BN254. The
+//! wrapper verifying key is shared by all programs; a program is identified
+//! by its vkey hash, which the wrapper proof takes as a public input. A registered key is therefore the 32-byte vkey hash followed by the
+//! wrapper verifying key as exported by SP1 in gnark's format
+//! (`groth16_vk.bin`). SP1's Plonk wrapper is not supported.
+//!
+//! gnark encodes integers big-endian and points compressed: the x coordinate
+//! big-endian, G2 coordinates imaginary part first, with the top two bits of
+//! the first byte flagging the point at infinity or which of the two square
+//! roots the y coordinate is.
+//!
+//! Proofs are verified against the prepared key with [`Sp1Groth16Verifier`].
+//! The second public input is the digest of the public values, which are the
+//! public inputs the pallet encodes for a message.
+
+use sp_std::prelude::*;
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::{
+    short_weierstrass::{Affine, SWCurveConfig},
+    AffineRepr,
+};
+use ark_ff::{PrimeField, Zero};
+use ark_groth16::{Groth16, Proof, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use sp_io::hashing::sha2_256;
+use crate::verification::{KeyPreparer, ProofVerifier, VerificationError, VerificationResult};
+
+/// Public inputs of SP1 wrapper proofs: the program vkey hash and the committed values digest
+pub const SP1_PUBLIC_INPUTS: usize = 2;
+
+/// Length of the program vkey hash leading a registered key
+pub const VKEY_HASH_LEN: usize = 32;
+
+const FIELD_LEN: usize = 32;
+const G1_LEN: usize = 32;
+const G2_LEN: usize = 64;
+
+const FLAG_MASK: u8 = 0b11 << 6;
+const FLAG_INFINITY: u8 = 0b01 << 6;
+const FLAG_SMALLEST: u8 = 0b10 << 6;
+const FLAG_LARGEST: u8 = 0b11 << 6;
+
+/// SP1 Groth16 wrapper keys
+///
+/// Prepared keys are the arkworks uncompressed encoding of the Groth16
+/// verifying key, which decodes without curve and subgroup checks.
+pub struct Sp1Groth16Key;
+
+impl KeyPreparer for Sp1Groth16Key {
+    fn prepare(key_bytes: &[u8]) -> Result<Vec<u8>, VerificationError> {
+        let key = parse_groth16_key(key_bytes)?;
+        let mut prepared = Vec::new();
+        key.serialize_uncompressed(&mut prepared).map_err(|_| VerificationError::SystemError)?;
+        Ok(prepared)
+    }
+}
+
+/// SP1 Groth16 wrapper proofs
+///
+/// Proofs are the points A, B and C uncompressed and big-endian, G2
+/// coordinates imaginary part first, as in the proof bytes SP1 exports for
+/// EVM verifiers without the leading verifier selector. The public inputs are
+/// the program vkey hash and [`committed_values_digest`] of the public inputs
+/// encoded by the pallet. Keys must be prepared by [`Sp1Groth16Key`].
+///
+/// The vkey hash identifies the program, so programs need not be cached.
+pub struct Sp1Groth16Verifier;
+
+impl ProofVerifier for Sp1Groth16Verifier {
+    fn requires_program() -> bool {
+        false
+    }
+
+    fn verify(
+        _program: &[u8],
+        program_hash: &[u8; 32],
+        prepared_key: &[u8],
+        proof: &[u8],
+        public_inputs: &[u8],
+    ) -> VerificationResult {
+        // Prepared keys were checked when they were registered
+        let key = VerifyingKey::<Bn254>::deserialize_uncompressed_unchecked(prepared_key)
+            .map_err(|_| VerificationError::SystemError)?;
+        let proof = parse_groth16_proof(proof)?;
+        let inputs = [field_from_be(program_hash)?, committed_values_digest(public_inputs)];
+
+        let valid = Groth16::<Bn254>::verify_proof(&Groth16::<Bn254>::prepare_verifying_key(&key), &proof, &inputs)
+            .map_err(|_| VerificationError::InvalidInput)?;
+        if !valid {
+            return Err(VerificationError::VerificationFailed);
+        }
+        Ok(())
+    }
+}
+
+/// Digest SP1 commits public values with: SHA-256 with the top three bits cleared
+pub fn committed_values_digest(public_values: &[u8]) -> Fr {
+    let mut digest = sha2_256(public_values);
+    digest[0] &= 0b0001_1111;
+    Fr::from_be_bytes_mod_order(&digest)
+}
+
+/// Parse an SP1 Groth16 proof
+///
+/// Every point must be canonically encoded, on the curve and in the
+/// prime-order subgroup, and no bytes may follow the proof.
+pub fn parse_groth16_proof(proof: &[u8]) -> Result<Proof<Bn254>, VerificationError> {
+    let mut reader = Reader::new(proof);
+    let a = reader.g1_uncompressed()?;
+    let b = reader.g2_uncompressed()?;
+    let c = reader.g1_uncompressed()?;
+    reader.finish()?;
+
+    Ok(Proof { a, b, c })
+}
+
+/// Parse a registered SP1 Groth16 key
+///
+/// Every point must be canonically encoded, on the curve and in the
+/// prime-order subgroup, the key must take [`SP1_PUBLIC_INPUTS`] public
+/// inputs without commitments, and no bytes may follow it.
+pub fn parse_groth16_key(key_bytes: &[u8]) -> Result<VerifyingKey<Bn254>, VerificationError> {
+    let mut reader = Reader::new(key_bytes);
+    reader.take(VKEY_HASH_LEN)?;
+
+    let alpha_g1 = reader.g1()?;
+    // gnark also stores beta and delta in G1, which verification does not use
+    reader.g1()?;
+    let beta_g2 = reader.g2()?;
+    let gamma_g2 = reader.g2()?;
+    reader.g1()?;
+    let delta_g2 = reader.g2()?;
+
+    let inputs = reader.u32()? as usize;
+    if inputs != SP1_PUBLIC_INPUTS + 1 {
+        return Err(VerificationError::InvalidInput);
+    }
+    let gamma_abc_g1 = (0..inputs).map(|_| reader.g1()).collect::<Result<Vec<_>, _>>()?;
+
+    // SP1 wrapper circuits have no commitments
+    if reader.u32()? != 0 {
+        return Err(VerificationError::InvalidInput);
+    }
+    reader.finish()?;
+
+    Ok(VerifyingKey { alpha_g1, beta_g2, gamma_g2, delta_g2, gamma_abc_g1 })
+}
+
+/// Reads gnark-encoded values from key bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], VerificationError> {
+        if self.bytes.len() < len {
+            return Err(VerificationError::InvalidInput);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, VerificationError> {
+        let bytes = self.take(4)?.try_into().map_err(|_| VerificationError::InvalidInput)?;
+        Ok(u32::from_be_bytes(bytes))
+    }
+
+    fn field<F: PrimeField>(&mut self) -> Result<F, VerificationError> {
+        field_from_be(self.take(FIELD_LEN)?)
+    }
+
+    fn g1(&mut self) -> Result<G1Affine, VerificationError> {
+        g1_from_compressed(self.take(G1_LEN)?)
+    }
+
+    fn g2(&mut self) -> Result<G2Affine, VerificationError> {
+        g2_from_compressed(self.take(G2_LEN)?)
+    }
+
+    fn g1_uncompressed(&mut self) -> Result<G1Affine, VerificationError> {
+        let x: Fq = self.field()?;
+        let y: Fq = self.field()?;
+        checked_point(x, y)
+    }
+
+    fn g2_uncompressed(&mut self) -> Result<G2Affine, VerificationError> {
+        let x_c1: Fq = self.field()?;
+        let x_c0: Fq = self.field()?;
+        let y_c1: Fq = self.field()?;
+        let y_c0: Fq = self.field()?;
+        checked_point(Fq2::new(x_c0, x_c1), Fq2::new(y_c0, y_c1))
+    }
+
+    /// Fail if any bytes are left
+    fn finish(self) -> Result<(), VerificationError> {
+        if !self.bytes.is_empty() {
+            return Err(VerificationError::InvalidInput);
+        }
+        Ok(())
+    }
+}
+
+/// Field element from its canonical big-endian encoding
+fn field_from_be<F: PrimeField>(bytes: &[u8]) -> Result<F, VerificationError> {
+    let mut le = bytes.to_vec();
+    le.reverse();
+    // Rejects encodings of values above the modulus
+    F::deserialize_uncompressed(&le[..]).map_err(|_| VerificationError::InvalidInput)
+}
+
+/// Point from its affine coordinates, with zero coordinates the point at infinity
+fn checked_point<P: SWCurveConfig>(x: P::BaseField, y: P::BaseField) -> Result<Affine<P>, VerificationError> {
+    if x.is_zero() && y.is_zero() {
+        return Ok(Affine::identity());
+    }
+    let point = Affine::<P>::new_unchecked(x, y);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(VerificationError::InvalidInput);
+    }
+    Ok(point)
+}
+
+/// Flag bits and the x coordinate bytes of a compressed point
+fn split_flag(bytes: &[u8]) -> (u8, Vec<u8>) {
+    let mut x = bytes.to_vec();
+    let flag = x[0] & FLAG_MASK;
+    x[0] &= !FLAG_MASK;
+    (flag, x)
+}
+
+fn g1_from_compressed(bytes: &[u8]) -> Result<G1Affine, VerificationError> {
+    let (flag, x) = split_flag(bytes);
+    let point = match flag {
+        FLAG_INFINITY if x.iter().all(|&b| b == 0) => G1Affine::zero(),
+        FLAG_SMALLEST | FLAG_LARGEST => {
+            let x: Fq = field_from_be(&x)?;
+            G1Affine::get_point_from_x_unchecked(x, flag == FLAG_LARGEST).ok_or(VerificationError::InvalidInput)?
+        }
+        _ => return Err(VerificationError::InvalidInput),
+    };
+    // Trivially true on BN254 G1, whose cofactor is one
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(VerificationError::InvalidInput);
+    }
+    Ok(point)
+}
+
+fn g2_from_compressed(bytes: &[u8]) -> Result<G2Affine, VerificationError> {
+    let (flag, x) = split_flag(bytes);
+    let point = match flag {
+        FLAG_INFINITY if x.iter().all(|&b| b == 0) => G2Affine::zero(),
+        FLAG_SMALLEST | FLAG_LARGEST => {
+            let c1: Fq = field_from_be(&x[..FIELD_LEN])?;
+            let c0: Fq = field_from_be(&x[FIELD_LEN..])?;
+            G2Affine::get_point_from_x_unchecked(Fq2::new(c0, c1), flag == FLAG_LARGEST)
+                .ok_or(VerificationError::InvalidInput)?
+        }
+        _ => return Err(VerificationError::InvalidInput),
+    };
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(VerificationError::InvalidInput);
+    }
+    Ok(point)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ark_ec::CurveGroup;
+    use ark_ff::{BigInteger, Field};
+
+    fn fq_bytes(x: Fq) -> Vec<u8> {
+        x.into_bigint().to_bytes_be()
+    }
+
+    fn g1_bytes(point: G1Affine) -> Vec<u8> {
+        let (x, y) = point.xy().unwrap();
+        let mut bytes = fq_bytes(*x);
+        bytes[0] |= if *y > -*y { FLAG_LARGEST } else { FLAG_SMALLEST };
+        bytes
+    }
+
+    fn g2_bytes(point: G2Affine) -> Vec<u8> {
+        let (x, y) = point.xy().unwrap();
+        let mut bytes = [fq_bytes(x.c1), fq_bytes(x.c0)].concat();
+        bytes[0] |= if *y > -*y { FLAG_LARGEST } else { FLAG_SMALLEST };
+        bytes
+    }
+
+    fn g1(scalar: u64) -> G1Affine {
+        (G1Affine::generator() * Fr::from(scalar)).into_affine()
+    }
+
+    fn g2(scalar: u64) -> G2Affine {
+        (G2Affine::generator() * Fr::from(scalar)).into_affine()
+    }
+
+    /// gnark encoding of a Groth16 key taking `inputs` public inputs
+    pub(crate) fn groth16_key(inputs: u64) -> Vec<u8> {
+        let mut key = vec![1; VKEY_HASH_LEN];
+        for part in [g1_bytes(g1(2)), g1_bytes(g1(3)), g2_bytes(g2(3)), g2_bytes(g2(5)), g1_bytes(g1(7)), g2_bytes(g2(7))] {
+            key.extend(part);
+        }
+        key.extend((inputs as u32 + 1).to_be_bytes());
+        for i in 0..=inputs {
+            key.extend(g1_bytes(g1(11 + i)));
+        }
+        key.extend(0u32.to_be_bytes());
+        key
+    }
+
+    /// Byte offset of the first point of a key
+    const POINTS: usize = VKEY_HASH_LEN;
+
+    #[test]
+    fn test_groth16_key_parses_and_prepares() {
+        let key = parse_groth16_key(&groth16_key(2)).unwrap();
+        assert_eq!(key.alpha_g1, g1(2));
+        assert_eq!(key.beta_g2, g2(3));
+        assert_eq!(key.gamma_g2, g2(5));
+        assert_eq!(key.delta_g2, g2(7));
+        assert_eq!(key.gamma_abc_g1, vec![g1(11), g1(12), g1(13)]);
+
+        let prepared = Sp1Groth16Key::prepare(&groth16_key(2)).unwrap();
+        assert_eq!(VerifyingKey::<Bn254>::deserialize_uncompressed_unchecked(&prepared[..]).unwrap(), key);
+    }
+
+    #[test]
+    fn test_groth16_key_rejects_truncation_and_trailing_bytes() {
+        let key = groth16_key(2);
+
+        assert!(Sp1Groth16Key::prepare(&key[..key.len() - 1]).is_err());
+        assert!(Sp1Groth16Key::prepare(&key[..VKEY_HASH_LEN]).is_err());
+        assert!(Sp1Groth16Key::prepare(&[key.clone(), vec![0]].concat()).is_err());
+    }
+
+    #[test]
+    fn test_groth16_key_rejects_wrong_input_count() {
+        assert!(Sp1Groth16Key::prepare(&groth16_key(1)).is_err());
+        assert!(Sp1Groth16Key::prepare(&groth16_key(3)).is_err());
+    }
+
+    #[test]
+    fn test_groth16_key_rejects_off_curve_point() {
+        // An x coordinate without a point on the curve
+        let x = (1u64..).map(Fq::from).find(|x| G1Affine::get_point_from_x_unchecked(*x, false).is_none()).unwrap();
+        let mut encoded = fq_bytes(x);
+        encoded[0] |= FLAG_SMALLEST;
+
+        let mut key = groth16_key(2);
+        key[POINTS..POINTS + G1_LEN].copy_from_slice(&encoded);
+        assert!(Sp1Groth16Key::prepare(&key).is_err());
+    }
+
+    #[test]
+    fn test_groth16_key_rejects_point_outside_subgroup() {
+        // A point on the twist that is not in the prime-order subgroup
+        let point = (1u64..)
+            .filter_map(|i| G2Affine::get_point_from_x_unchecked(Fq2::new(Fq::from(i), Fq::zero()), false))
+            .find(|point| !point.is_in_correct_subgroup_assuming_on_curve())
+            .unwrap();
+
+        let mut key = groth16_key(2);
+        let beta_g2 = POINTS + 2 * G1_LEN;
+        key[beta_g2..beta_g2 + G2_LEN].copy_from_slice(&g2_bytes(point));
+        assert!(Sp1Groth16Key::prepare(&key).is_err());
+    }
+
+    #[test]
+    fn test_groth16_key_rejects_non_canonical_encoding() {
+        // x coordinate above the field modulus
+        let mut key = groth16_key(2);
+        key[POINTS..POINTS + G1_LEN].copy_from_slice(&[0xff; G1_LEN]);
+        key[POINTS] = FLAG_SMALLEST | 0x3f;
+        assert!(Sp1Groth16Key::prepare(&key).is_err());
+
+        // Uncompressed flag on a compressed point
+        let mut key = groth16_key(2);
+        key[POINTS] &= !FLAG_MASK;
+        assert!(Sp1Groth16Key::prepare(&key).is_err());
+
+        // Infinity with a non-zero coordinate
+        let mut key = groth16_key(2);
+        key[POINTS] = (key[POINTS] & !FLAG_MASK) | FLAG_INFINITY;
+        assert!(Sp1Groth16Key::prepare(&key).is_err());
+    }
+
+    fn g1_uncompressed_bytes(point: G1Affine) -> Vec<u8> {
+        let (x, y) = point.xy().unwrap();
+        [fq_bytes(*x), fq_bytes(*y)].concat()
+    }
+
+    fn g2_uncompressed_bytes(point: G2Affine) -> Vec<u8> {
+        let (x, y) = point.xy().unwrap();
+        [fq_bytes(x.c1), fq_bytes(x.c0), fq_bytes(y.c1), fq_bytes(y.c0)].concat()
+    }
+
+    fn g1_fr(scalar: Fr) -> G1Affine {
+        (G1Affine::generator() * scalar).into_affine()
+    }
+
+    /// Proof of `public_values` for `groth16_key(2)`, whose vkey hash is all ones
+    ///
+    /// With the key's discrete logs the pairing check reduces to
+    /// `a = 2 * 3 + 5 * l + 7 * c` for `B = g2(1)`, where `l` is the
+    /// input-weighted sum of the `gamma_abc` scalars 11, 12 and 13.
+    pub(crate) fn groth16_proof(public_values: &[u8]) -> Vec<u8> {
+        let vkey_hash: Fr = field_from_be(&[1; VKEY_HASH_LEN]).unwrap();
+        let l = Fr::from(11u64) + Fr::from(12u64) * vkey_hash + Fr::from(13u64) * committed_values_digest(public_values);
+        let a = Fr::from(1u64);
+        let c = (a - Fr::from(6u64) - Fr::from(5u64) * l) * Fr::from(7u64).inverse().unwrap();
+        [g1_uncompressed_bytes(g1_fr(a)), g2_uncompressed_bytes(g2(1)), g1_uncompressed_bytes(g1_fr(c))].concat()
+    }
+
+    fn verify(proof: &[u8], public_values: &[u8]) -> VerificationResult {
+        let prepared = Sp1Groth16Key::prepare(&groth16_key(2)).unwrap();
+        Sp1Groth16Verifier::verify(&[], &[1; 32], &prepared, proof, public_values)
+    }
+
+    #[test]
+    fn test_groth16_proof_verifies_against_prepared_key() {
+        let proof = groth16_proof(b"message");
+        assert_eq!(verify(&proof, b"message"), Ok(()));
+
+        // Other public values or another program's vkey hash fail
+        assert_eq!(verify(&proof, b"other message"), Err(VerificationError::VerificationFailed));
+        let prepared = Sp1Groth16Key::prepare(&groth16_key(2)).unwrap();
+        assert_eq!(
+            Sp1Groth16Verifier::verify(&[], &[2; 32], &prepared, &proof, b"message"),
+            Err(VerificationError::VerificationFailed)
+        );
+    }
+
+    #[test]
+    fn test_groth16_proof_rejects_malformed_points() {
+        let proof = groth16_proof(b"message");
+
+        assert_eq!(verify(&proof[..proof.len() - 1], b"message"), Err(VerificationError::InvalidInput));
+        assert_eq!(verify(&[proof.clone(), vec![0]].concat(), b"message"), Err(VerificationError::InvalidInput));
+
+        // A off the curve
+        let mut off_curve = proof.clone();
+        off_curve[2 * FIELD_LEN - 1] ^= 1;
+        assert_eq!(verify(&off_curve, b"message"), Err(VerificationError::InvalidInput));
+
+        // C above the field modulus
+        let mut non_canonical = proof;
+        let c = 6 * FIELD_LEN;
+        non_canonical[c..c + FIELD_LEN].copy_from_slice(&[0xff; FIELD_LEN]);
+        assert_eq!(verify(&non_canonical, b"message"), Err(VerificationError::InvalidInput));
+    }
+}
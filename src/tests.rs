@@ -0,0 +1,1534 @@
+//! Dispatch tests for the Frostgate verifier pallet
+
+use crate::{
+    keys::{key_registration_payload, program_claim_commitment, KeyMetadata, KeyMetadataInput, KeyOwnershipProof},
+    message_id::Blake2Scale,
+    mock::*,
+    pallet::*,
+    sp1_keys::tests::{groth16_key, groth16_proof},
+    upload::UploadTarget,
+    verification::encode_public_inputs,
+};
+use frame_support::{
+    assert_noop, assert_ok,
+    traits::{fungible::InspectHold, GenesisBuild, Get, Hooks},
+    weights::Weight,
+};
+use sp_runtime::{testing::TestSignature, DispatchError};
+
+const OTHER_PROGRAM: [u8; 32] = [8; 32];
+
+fn submit(sender: u64, nonce: u64, program_hash: [u8; 32]) -> sp_runtime::DispatchResult {
+    Verifier::submit_message(
+        RuntimeOrigin::signed(sender),
+        ETHEREUM,
+        POLKADOT,
+        0,
+        nonce,
+        [1; 32],
+        program_hash,
+        vec![0, 1, 2],
+        None,
+        None,
+    )
+}
+
+/// ID of the last message submitted
+fn last_message_id() -> MessageId {
+    System::events()
+        .into_iter()
+        .rev()
+        .find_map(|record| match record.event {
+            RuntimeEvent::Verifier(Event::MessageSubmitted { hash, .. }) => Some(hash),
+            _ => None,
+        })
+        .expect("no message was submitted")
+}
+
+#[test]
+fn test_submit_requires_route() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(submit(ALICE, 0, PROGRAM), Error::<Test>::RouteNotFound);
+    });
+}
+
+#[test]
+fn test_submit_requires_allowed_program() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[PROGRAM])));
+
+        assert_noop!(submit(ALICE, 0, OTHER_PROGRAM), Error::<Test>::ProgramNotAllowed);
+        assert_ok!(submit(ALICE, 0, PROGRAM));
+    });
+}
+
+#[test]
+fn test_empty_allowlist_closes_route() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[])));
+
+        assert_noop!(submit(ALICE, 0, PROGRAM), Error::<Test>::ProgramNotAllowed);
+    });
+}
+
+#[test]
+fn test_set_route_validates_config() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Verifier::set_route(RuntimeOrigin::signed(ALICE), ETHEREUM, POLKADOT, route(&[PROGRAM])),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, 99, route(&[PROGRAM])),
+            Error::<Test>::ChainNotRegistered
+        );
+
+        let mut config = route(&[PROGRAM]);
+        config.max_payload_size = 257;
+        assert_noop!(
+            Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, config),
+            Error::<Test>::InvalidMaxPayloadSize
+        );
+
+        let mut config = route(&[PROGRAM]);
+        config.deposit = 9;
+        assert_noop!(
+            Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, config),
+            Error::<Test>::DepositTooLow
+        );
+    });
+}
+
+#[test]
+fn test_remove_route_closes_route() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Verifier::remove_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT),
+            Error::<Test>::RouteNotFound
+        );
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[PROGRAM])));
+        assert_ok!(Verifier::remove_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT));
+
+        assert_noop!(submit(ALICE, 0, PROGRAM), Error::<Test>::RouteNotFound);
+    });
+}
+
+#[test]
+fn test_chain_registry_requires_chain_admin() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Verifier::register_chain(RuntimeOrigin::signed(ALICE), 3, b"Solana".to_vec(), 256),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            Verifier::update_chain(RuntimeOrigin::signed(ALICE), ETHEREUM, Some(false), None),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(Verifier::deregister_chain(RuntimeOrigin::signed(ALICE), ETHEREUM), DispatchError::BadOrigin);
+
+        assert_ok!(Verifier::register_chain(RuntimeOrigin::root(), 3, b"Solana".to_vec(), 256));
+        assert_ok!(Verifier::update_chain(RuntimeOrigin::root(), 3, Some(false), Some(128)));
+        assert_eq!(Chains::<Test>::get(3).map(|info| (info.enabled, info.max_payload_size)), Some((false, 128)));
+        assert_ok!(Verifier::deregister_chain(RuntimeOrigin::root(), 3));
+        assert!(!Chains::<Test>::contains_key(3));
+    });
+}
+
+#[test]
+fn test_deregister_chain_refuses_while_in_use() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[PROGRAM])));
+        assert_ok!(Verifier::pause(RuntimeOrigin::root(), PauseScope::Route(ETHEREUM, POLKADOT), vec![]));
+        assert_noop!(Verifier::deregister_chain(RuntimeOrigin::root(), POLKADOT), Error::<Test>::ChainInUse);
+
+        // Removing the route lifts its pause and releases both chains
+        assert_ok!(Verifier::remove_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT));
+        assert!(!Paused::<Test>::contains_key(PauseScope::Route(ETHEREUM, POLKADOT)));
+        assert_eq!(ChainRouteCount::<Test>::get(ETHEREUM), 0);
+
+        assert_ok!(Verifier::pause(RuntimeOrigin::root(), PauseScope::Chain(POLKADOT), vec![]));
+        assert_noop!(Verifier::deregister_chain(RuntimeOrigin::root(), POLKADOT), Error::<Test>::ChainInUse);
+        assert_ok!(Verifier::unpause(RuntimeOrigin::root(), PauseScope::Chain(POLKADOT)));
+
+        assert_ok!(Verifier::set_volume_limit(RuntimeOrigin::root(), POLKADOT, Some(100)));
+        assert_noop!(Verifier::deregister_chain(RuntimeOrigin::root(), POLKADOT), Error::<Test>::ChainInUse);
+        assert_ok!(Verifier::set_volume_limit(RuntimeOrigin::root(), POLKADOT, None));
+
+        // Lane nonces stay, so the chain can only be disabled
+        InboundLaneNonces::<Test>::insert(ETHEREUM, 0, 1);
+        assert_noop!(Verifier::deregister_chain(RuntimeOrigin::root(), ETHEREUM), Error::<Test>::ChainInUse);
+
+        assert_ok!(Verifier::deregister_chain(RuntimeOrigin::root(), POLKADOT));
+        assert_noop!(
+            Verifier::pause(RuntimeOrigin::root(), PauseScope::Chain(POLKADOT), vec![]),
+            Error::<Test>::ChainNotRegistered
+        );
+    });
+}
+
+#[test]
+fn test_message_id_commits_to_program_hash() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Verifier::set_route(
+            RuntimeOrigin::root(),
+            ETHEREUM,
+            POLKADOT,
+            route(&[PROGRAM, OTHER_PROGRAM])
+        ));
+
+        assert_ok!(submit(ALICE, 0, PROGRAM));
+        let first = last_message_id();
+        // The same message naming another program is a distinct message
+        assert_ok!(submit(ALICE, 0, OTHER_PROGRAM));
+        let second = last_message_id();
+
+        assert_ne!(first, second);
+        assert_eq!(Messages::<Test>::get(first).unwrap().program_hash, PROGRAM);
+        assert_eq!(Messages::<Test>::get(second).unwrap().program_hash, OTHER_PROGRAM);
+    });
+}
+
+#[test]
+fn test_verify_rejects_program_removed_from_allowlist() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[PROGRAM])));
+        assert_ok!(submit(ALICE, 0, PROGRAM));
+        let id = last_message_id();
+
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[OTHER_PROGRAM])));
+
+        assert_noop!(
+            Verifier::verify_message(RuntimeOrigin::signed(BOB), id),
+            Error::<Test>::ProgramNotAllowed
+        );
+    });
+}
+
+#[test]
+fn test_sender_rate_limit() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[PROGRAM])));
+        for nonce in 0..5 {
+            assert_ok!(submit(ALICE, nonce, PROGRAM));
+        }
+
+        assert_noop!(submit(ALICE, 5, PROGRAM), Error::<Test>::RateLimited);
+        // Other senders have their own allowance
+        assert_ok!(submit(BOB, 5, PROGRAM));
+
+        // Half the window frees half the allowance
+        System::set_block_number(6);
+        assert_ok!(submit(ALICE, 6, PROGRAM));
+    });
+}
+
+#[test]
+fn test_block_rate_limit() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[PROGRAM])));
+        for nonce in 0..10 {
+            let sender = [ALICE, BOB, CHARLIE][nonce as usize % 3];
+            assert_ok!(submit(sender, nonce, PROGRAM));
+        }
+
+        assert_noop!(submit(CHARLIE, 10, PROGRAM), Error::<Test>::RateLimited);
+
+        System::set_block_number(2);
+        assert_ok!(submit(CHARLIE, 10, PROGRAM));
+    });
+}
+
+#[test]
+fn test_volume_is_not_charged_on_submission() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[PROGRAM])));
+        assert_ok!(Verifier::set_volume_limit(RuntimeOrigin::root(), ETHEREUM, Some(1)));
+
+        // Pending transfers above the cap may be submitted and cancelled freely
+        let transfer = || {
+            Verifier::submit_message(
+                RuntimeOrigin::signed(ALICE),
+                ETHEREUM,
+                POLKADOT,
+                0,
+                0,
+                [1; 32],
+                PROGRAM,
+                vec![5],
+                None,
+                None,
+            )
+        };
+        assert_ok!(transfer());
+        assert_ok!(Verifier::cancel_message(RuntimeOrigin::signed(ALICE), last_message_id()));
+        assert_ok!(transfer());
+
+        assert_eq!(ChainVolumeCounters::<Test>::get(ETHEREUM), Default::default());
+    });
+}
+
+#[test]
+fn test_set_volume_limit() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Verifier::set_volume_limit(RuntimeOrigin::signed(ALICE), ETHEREUM, Some(100)),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            Verifier::set_volume_limit(RuntimeOrigin::root(), 99, Some(100)),
+            Error::<Test>::ChainNotRegistered
+        );
+
+        assert_ok!(Verifier::set_volume_limit(RuntimeOrigin::root(), ETHEREUM, Some(100)));
+        assert_eq!(ChainVolumeLimits::<Test>::get(ETHEREUM), Some(100));
+        assert_ok!(Verifier::set_volume_limit(RuntimeOrigin::root(), ETHEREUM, None));
+        assert_eq!(ChainVolumeLimits::<Test>::get(ETHEREUM), None);
+    });
+}
+
+#[test]
+fn test_on_idle_prunes_decayed_sender_counters() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[PROGRAM])));
+        assert_ok!(submit(ALICE, 0, PROGRAM));
+        System::set_block_number(5);
+        assert_ok!(submit(BOB, 1, PROGRAM));
+
+        System::set_block_number(11);
+        Verifier::on_idle(11, Weight::MAX);
+
+        // Alice's counter decayed fully, Bob's is still in use
+        assert!(!SenderRateCounters::<Test>::contains_key(ALICE));
+        assert!(SenderRateCounters::<Test>::contains_key(BOB));
+        assert!(RateCounterCursor::<Test>::get().is_none());
+    });
+}
+
+fn claim_program(who: u64) -> sp_runtime::DispatchResult {
+    Verifier::register_verification_key(
+        RuntimeOrigin::signed(who),
+        TestKeys::program_hash(),
+        TestKeys::key(),
+        None,
+        KeyOwnershipProof::ProgramBytes(TestKeys::program()),
+    )
+}
+
+fn commit_claim(who: u64) {
+    let commitment = program_claim_commitment(&who, &TestKeys::program());
+    assert_ok!(Verifier::commit_program_claim(RuntimeOrigin::signed(who), commitment));
+}
+
+#[test]
+fn test_program_claim_requires_earlier_commitment() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(claim_program(ALICE), Error::<Test>::ClaimNotCommitted);
+
+        // A commitment from the same block does not count
+        commit_claim(ALICE);
+        assert_noop!(claim_program(ALICE), Error::<Test>::ClaimNotCommitted);
+
+        System::set_block_number(2);
+        assert_ok!(claim_program(ALICE));
+
+        assert_eq!(ProgramOwners::<Test>::get(TestKeys::program_hash()), Some(ALICE));
+        assert!(ProgramClaims::<Test>::get(ALICE).is_none());
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE - 50);
+    });
+}
+
+#[test]
+fn test_program_claim_deposit_held_until_reveal() {
+    new_test_ext().execute_with(|| {
+        commit_claim(ALICE);
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE - 5);
+
+        // Replacing the commitment keeps a single deposit on hold
+        commit_claim(ALICE);
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE - 5);
+        assert_eq!(ProgramClaims::<Test>::get(ALICE).map(|(_, _, deposit)| deposit), Some(5));
+
+        System::set_block_number(2);
+        assert_ok!(claim_program(ALICE));
+        // Only the key deposit stays on hold
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE - 50);
+    });
+}
+
+#[test]
+fn test_expired_program_claims_are_rejected_and_removed() {
+    new_test_ext().execute_with(|| {
+        commit_claim(ALICE);
+        commit_claim(BOB);
+        System::set_block_number(15);
+        commit_claim(BOB);
+
+        System::set_block_number(22);
+        assert_noop!(claim_program(ALICE), Error::<Test>::ClaimNotCommitted);
+
+        Verifier::on_idle(22, Weight::MAX);
+
+        // Alice's claim timed out, Bob's was renewed
+        assert!(ProgramClaims::<Test>::get(ALICE).is_none());
+        assert!(ProgramClaims::<Test>::get(BOB).is_some());
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE);
+        assert_eq!(Balances::free_balance(BOB), INITIAL_BALANCE - 5);
+        System::assert_has_event(Event::ProgramClaimExpired { who: ALICE, deposit_released: 5 }.into());
+        assert!(ClaimCursor::<Test>::get().is_none());
+    });
+}
+
+#[test]
+fn test_revealed_program_bytes_cannot_be_front_run() {
+    new_test_ext().execute_with(|| {
+        commit_claim(ALICE);
+        System::set_block_number(2);
+
+        // Bob copies the bytes from Alice's pending claim
+        assert_noop!(claim_program(BOB), Error::<Test>::ClaimNotCommitted);
+        commit_claim(BOB);
+        assert_noop!(claim_program(BOB), Error::<Test>::ClaimNotCommitted);
+
+        // Alice's claim lands first, so Bob's later one is rejected
+        assert_ok!(claim_program(ALICE));
+        System::set_block_number(3);
+        assert_noop!(claim_program(BOB), Error::<Test>::NotProgramOwner);
+    });
+}
+
+#[test]
+fn test_program_with_commitment_of_other_bytes_cannot_be_claimed() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Verifier::commit_program_claim(
+            RuntimeOrigin::signed(ALICE),
+            program_claim_commitment(&ALICE, b"other program")
+        ));
+        System::set_block_number(2);
+
+        assert_noop!(claim_program(ALICE), Error::<Test>::ClaimNotCommitted);
+    });
+}
+
+#[test]
+fn test_owner_cannot_rotate_out_governance_key() {
+    new_test_ext().execute_with(|| {
+        commit_claim(ALICE);
+        System::set_block_number(2);
+        assert_ok!(claim_program(ALICE));
+
+        assert_ok!(Verifier::add_verification_key(
+            RuntimeOrigin::root(),
+            TestKeys::program_hash(),
+            TestKeys::key(),
+            None
+        ));
+
+        assert_noop!(claim_program(ALICE), Error::<Test>::GovernanceKeyActive);
+        let payload = key_registration_payload(&TestKeys::program_hash(), 2, 1, &TestKeys::key());
+        assert_noop!(
+            Verifier::register_verification_key(
+                RuntimeOrigin::signed(BOB),
+                TestKeys::program_hash(),
+                TestKeys::key(),
+                None,
+                KeyOwnershipProof::Signature(TestSignature(ALICE, payload)),
+            ),
+            Error::<Test>::GovernanceKeyActive
+        );
+
+        // The key scheduled before the governance key cannot replace it either
+        System::set_block_number(12);
+        assert_noop!(
+            Verifier::activate_scheduled_key(RuntimeOrigin::signed(ALICE), TestKeys::program_hash()),
+            Error::<Test>::GovernanceKeyActive
+        );
+    });
+}
+
+#[test]
+fn test_owner_signature_registers_key() {
+    new_test_ext().execute_with(|| {
+        commit_claim(ALICE);
+        System::set_block_number(2);
+        assert_ok!(claim_program(ALICE));
+        System::set_block_number(12);
+        assert_ok!(Verifier::activate_scheduled_key(RuntimeOrigin::signed(ALICE), TestKeys::program_hash()));
+
+        let payload = key_registration_payload(&TestKeys::program_hash(), 2, 1, &TestKeys::key());
+        assert_ok!(Verifier::register_verification_key(
+            RuntimeOrigin::signed(BOB),
+            TestKeys::program_hash(),
+            TestKeys::key(),
+            None,
+            KeyOwnershipProof::Signature(TestSignature(ALICE, payload)),
+        ));
+
+        System::set_block_number(22);
+        assert_ok!(Verifier::activate_scheduled_key(RuntimeOrigin::signed(BOB), TestKeys::program_hash()));
+
+        assert_eq!(ActiveKeyVersions::<Test>::get(TestKeys::program_hash()).unwrap().active, 2);
+        assert_eq!(KeyDeposits::<Test>::get(TestKeys::program_hash(), 2), Some((BOB, 50)));
+    });
+}
+
+#[test]
+fn test_owner_signature_cannot_be_replayed_after_cancel() {
+    new_test_ext().execute_with(|| {
+        commit_claim(ALICE);
+        System::set_block_number(2);
+        assert_ok!(claim_program(ALICE));
+        System::set_block_number(12);
+        assert_ok!(Verifier::activate_scheduled_key(RuntimeOrigin::signed(ALICE), TestKeys::program_hash()));
+
+        let payload = key_registration_payload(&TestKeys::program_hash(), 2, 1, &TestKeys::key());
+        let register = || {
+            Verifier::register_verification_key(
+                RuntimeOrigin::signed(BOB),
+                TestKeys::program_hash(),
+                TestKeys::key(),
+                None,
+                KeyOwnershipProof::Signature(TestSignature(ALICE, payload.clone())),
+            )
+        };
+        assert_ok!(register());
+        assert_eq!(KeyRegistrationNonces::<Test>::get(TestKeys::program_hash()), 2);
+
+        // The pause origin stops the key, and the same signature cannot schedule it again
+        assert_ok!(Verifier::cancel_scheduled_key(RuntimeOrigin::root(), TestKeys::program_hash()));
+        assert_noop!(register(), Error::<Test>::InvalidOwnershipProof);
+    });
+}
+
+#[test]
+fn test_depositor_removes_rotated_out_key() {
+    new_test_ext().execute_with(|| {
+        commit_claim(ALICE);
+        System::set_block_number(2);
+        assert_ok!(claim_program(ALICE));
+        System::set_block_number(12);
+        assert_ok!(Verifier::activate_scheduled_key(RuntimeOrigin::signed(ALICE), TestKeys::program_hash()));
+        assert_ok!(claim_program(ALICE));
+        System::set_block_number(22);
+        assert_ok!(Verifier::activate_scheduled_key(RuntimeOrigin::signed(ALICE), TestKeys::program_hash()));
+
+        // The replaced key is accepted until the grace period ends
+        assert_noop!(
+            Verifier::remove_verification_key(RuntimeOrigin::signed(ALICE), TestKeys::program_hash(), 1),
+            Error::<Test>::KeyStillInUse
+        );
+
+        System::set_block_number(43);
+        assert_noop!(
+            Verifier::remove_verification_key(RuntimeOrigin::signed(BOB), TestKeys::program_hash(), 1),
+            Error::<Test>::NotProgramOwner
+        );
+        assert_noop!(
+            Verifier::remove_verification_key(RuntimeOrigin::signed(ALICE), TestKeys::program_hash(), 2),
+            Error::<Test>::KeyStillInUse
+        );
+        assert_ok!(Verifier::remove_verification_key(RuntimeOrigin::signed(ALICE), TestKeys::program_hash(), 1));
+
+        assert!(VerificationKeys::<Test>::get(TestKeys::program_hash(), 1).is_none());
+        assert!(KeyDeposits::<Test>::get(TestKeys::program_hash(), 1).is_none());
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE - 50);
+    });
+}
+
+#[test]
+fn test_registered_key_waits_for_activation_delay() {
+    new_test_ext().execute_with(|| {
+        commit_claim(ALICE);
+        System::set_block_number(2);
+        assert_ok!(claim_program(ALICE));
+
+        assert!(ActiveKeyVersions::<Test>::get(TestKeys::program_hash()).is_none());
+        assert_eq!(ScheduledKeyDeposits::<Test>::get(TestKeys::program_hash()), Some((ALICE, 50)));
+        assert_noop!(claim_program(ALICE), Error::<Test>::KeyAlreadyScheduled);
+
+        System::set_block_number(11);
+        assert_noop!(
+            Verifier::activate_scheduled_key(RuntimeOrigin::signed(BOB), TestKeys::program_hash()),
+            Error::<Test>::ActivationNotDue
+        );
+
+        System::set_block_number(12);
+        assert_ok!(Verifier::activate_scheduled_key(RuntimeOrigin::signed(BOB), TestKeys::program_hash()));
+        assert_eq!(ActiveKeyVersions::<Test>::get(TestKeys::program_hash()).unwrap().active, 1);
+        assert_eq!(KeyDeposits::<Test>::get(TestKeys::program_hash(), 1), Some((ALICE, 50)));
+        assert!(ScheduledKeyDeposits::<Test>::get(TestKeys::program_hash()).is_none());
+    });
+}
+
+#[test]
+fn test_depositor_can_cancel_registered_key() {
+    new_test_ext().execute_with(|| {
+        commit_claim(ALICE);
+        System::set_block_number(2);
+        assert_ok!(claim_program(ALICE));
+
+        assert_noop!(
+            Verifier::cancel_scheduled_key(RuntimeOrigin::signed(BOB), TestKeys::program_hash()),
+            DispatchError::BadOrigin
+        );
+        assert_ok!(Verifier::cancel_scheduled_key(RuntimeOrigin::signed(ALICE), TestKeys::program_hash()));
+
+        assert!(ScheduledKeys::<Test>::get(TestKeys::program_hash()).is_none());
+        assert!(ScheduledKeyDeposits::<Test>::get(TestKeys::program_hash()).is_none());
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE);
+    });
+}
+
+#[test]
+fn test_add_verification_key_requires_emergency_origin() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Verifier::add_verification_key(
+                RuntimeOrigin::signed(ALICE),
+                TestKeys::program_hash(),
+                TestKeys::key(),
+                None
+            ),
+            DispatchError::BadOrigin
+        );
+
+        assert_ok!(Verifier::add_verification_key(
+            RuntimeOrigin::root(),
+            TestKeys::program_hash(),
+            TestKeys::key(),
+            None
+        ));
+        assert_eq!(ActiveKeyVersions::<Test>::get(TestKeys::program_hash()).unwrap().active, 1);
+    });
+}
+
+#[test]
+fn test_key_must_derive_program_hash() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Verifier::add_verification_key(RuntimeOrigin::root(), PROGRAM, TestKeys::key(), None),
+            Error::<Test>::ProgramHashMismatch
+        );
+        // Too short to name its program
+        assert_noop!(
+            Verifier::add_verification_key(RuntimeOrigin::root(), PROGRAM, vec![7; 16], None),
+            Error::<Test>::InvalidKey
+        );
+
+        assert_ok!(Verifier::add_verification_key(
+            RuntimeOrigin::root(),
+            TestKeys::program_hash(),
+            TestKeys::key(),
+            None
+        ));
+    });
+}
+
+#[test]
+fn test_program_must_hash_to_program_hash() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Verifier::cache_program(RuntimeOrigin::root(), PROGRAM, TestKeys::program()),
+            Error::<Test>::ProgramHashMismatch
+        );
+
+        // Programs are cached under the hash their keys are registered with
+        assert_ok!(Verifier::add_verification_key(
+            RuntimeOrigin::root(),
+            TestKeys::program_hash(),
+            TestKeys::key(),
+            None
+        ));
+        assert_ok!(Verifier::cache_program(RuntimeOrigin::root(), TestKeys::program_hash(), TestKeys::program()));
+        assert!(ProgramCache::<Test>::contains_key(TestKeys::program_hash()));
+    });
+}
+
+fn metadata(circuit_name: &[u8]) -> KeyMetadataInput<u64> {
+    KeyMetadata {
+        circuit_name: circuit_name.to_vec().try_into().unwrap(),
+        circuit_version: b"1.0.0".to_vec().try_into().unwrap(),
+        source_commit: b"0123abcd".to_vec().try_into().unwrap(),
+        public_input_layout: b"message id preimage".to_vec().try_into().unwrap(),
+        setup_transcript_hash: Some([3; 32]),
+        owner: Some(ALICE),
+    }
+}
+
+#[test]
+fn test_key_metadata_fields_are_bounded() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Verifier::add_verification_key(
+                RuntimeOrigin::root(),
+                TestKeys::program_hash(),
+                TestKeys::key(),
+                Some(metadata(&[b'x'; 65]))
+            ),
+            Error::<Test>::MetadataTooLarge
+        );
+
+        assert_ok!(Verifier::add_verification_key(
+            RuntimeOrigin::root(),
+            TestKeys::program_hash(),
+            TestKeys::key(),
+            Some(metadata(b"frostgate-message"))
+        ));
+        let entry = VerificationKeys::<Test>::get(TestKeys::program_hash(), 1).unwrap();
+        assert_eq!(entry.metadata, metadata(b"frostgate-message").rebound());
+    });
+}
+
+#[test]
+fn test_all_verification_keys_lists_every_program() {
+    new_test_ext().execute_with(|| {
+        let other_program = b"other program".to_vec();
+        let other_hash = sp_io::hashing::blake2_256(&other_program);
+        let other_key = [&other_hash[..], b"other key"].concat();
+
+        assert_ok!(Verifier::add_verification_key(
+            RuntimeOrigin::root(),
+            TestKeys::program_hash(),
+            TestKeys::key(),
+            None
+        ));
+        assert_ok!(Verifier::add_verification_key(
+            RuntimeOrigin::root(),
+            TestKeys::program_hash(),
+            TestKeys::key(),
+            None
+        ));
+        assert_ok!(Verifier::add_verification_key(RuntimeOrigin::root(), other_hash, other_key, None));
+
+        let mut keys: Vec<_> = Verifier::all_verification_keys()
+            .into_iter()
+            .map(|(program_hash, version, _)| (program_hash, version))
+            .collect();
+        keys.sort();
+        let mut expected = vec![(TestKeys::program_hash(), 1), (TestKeys::program_hash(), 2), (other_hash, 1)];
+        expected.sort();
+        assert_eq!(keys, expected);
+    });
+}
+
+/// Genesis configuration of a devnet with one route and a preloaded program
+fn devnet_genesis() -> GenesisConfig<Test> {
+    GenesisConfig {
+        chains: vec![(ETHEREUM, b"Ethereum".to_vec(), 256), (POLKADOT, b"Polkadot".to_vec(), 128)],
+        verification_keys: vec![TestKeys::key()],
+        programs: vec![TestKeys::program()],
+        routes: vec![(ETHEREUM, POLKADOT, 128, vec![TestKeys::program_hash()])],
+        inbound_lane_nonces: vec![(ETHEREUM, 0, 5)],
+        ..Default::default()
+    }
+}
+
+fn genesis_ext(config: GenesisConfig<Test>) -> sp_io::TestExternalities {
+    let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    config.assimilate_storage(&mut storage).unwrap();
+    sp_io::TestExternalities::new(storage)
+}
+
+#[test]
+fn test_genesis_preloads_bridge() {
+    genesis_ext(devnet_genesis()).execute_with(|| {
+        let program_hash = TestKeys::program_hash();
+
+        assert_eq!(Chains::<Test>::get(POLKADOT).unwrap().max_payload_size, 128);
+        let route = Routes::<Test>::get(ETHEREUM, POLKADOT).unwrap();
+        assert_eq!(route.deposit, 10);
+        assert!(route.allows_program(&program_hash));
+
+        // The key and the program are stored under the same program hash
+        assert_eq!(ActiveKeyVersions::<Test>::get(program_hash).unwrap().active, 1);
+        assert!(VerificationKeys::<Test>::contains_key(program_hash, 1));
+        assert!(ProgramCache::<Test>::contains_key(program_hash));
+
+        assert_eq!(InboundLaneNonces::<Test>::get(ETHEREUM, 0), 5);
+    });
+}
+
+#[test]
+#[should_panic(expected = "genesis chain max payload size exceeds MaxPayloadSize")]
+fn test_genesis_rejects_oversized_chain_payload() {
+    let mut config = devnet_genesis();
+    config.chains[0].2 = 257;
+    genesis_ext(config);
+}
+
+#[test]
+#[should_panic(expected = "genesis program has no genesis verification key")]
+fn test_genesis_rejects_program_without_key() {
+    let mut config = devnet_genesis();
+    config.programs.push(b"other program".to_vec());
+    genesis_ext(config);
+}
+
+/// Proof under `key` of the test program message with `nonce`
+fn test_proof(nonce: u64, key: &[u8]) -> Vec<u8> {
+    let public_inputs =
+        encode_public_inputs::<Blake2Scale>(ETHEREUM, POLKADOT, 0, nonce, [1; 32], TestKeys::program_hash(), &[0, 1, 2]);
+    TestProofs::prove(key, &public_inputs)
+}
+
+/// Submit a message for the test program with a proof under `key`
+fn submit_proven(sender: u64, nonce: u64, key: &[u8]) -> sp_runtime::DispatchResult {
+    Verifier::submit_message(
+        RuntimeOrigin::signed(sender),
+        ETHEREUM,
+        POLKADOT,
+        0,
+        nonce,
+        [1; 32],
+        TestKeys::program_hash(),
+        vec![0, 1, 2],
+        Some(test_proof(nonce, key)),
+        None,
+    )
+}
+
+/// Route, key and cached program for the test program
+fn setup_test_program() {
+    assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[TestKeys::program_hash()])));
+    assert_ok!(Verifier::add_verification_key(
+        RuntimeOrigin::root(),
+        TestKeys::program_hash(),
+        TestKeys::key(),
+        None
+    ));
+    assert_ok!(Verifier::cache_program(RuntimeOrigin::root(), TestKeys::program_hash(), TestKeys::program()));
+}
+
+#[test]
+fn test_verify_message_checks_proof_against_prepared_key() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+
+        // A proof under another key fails
+        assert_ok!(submit_proven(ALICE, 0, b"other key"));
+        let id = last_message_id();
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), id));
+        assert_eq!(Messages::<Test>::get(id).unwrap().status, MessageStatus::Failed);
+        assert_eq!(ProgramMessageCount::<Test>::get(TestKeys::program_hash()), 1);
+
+        assert_ok!(Verifier::attach_proof(RuntimeOrigin::signed(ALICE), id, test_proof(0, &TestKeys::key())));
+
+        let message = Messages::<Test>::get(id).unwrap();
+        assert_eq!(message.status, MessageStatus::Verified);
+        assert_eq!(message.key_version, Some(1));
+        assert_eq!(ProgramCacheUsage::<Test>::get(TestKeys::program_hash()).unwrap().use_count, 1);
+        assert!(!ProgramMessageCount::<Test>::contains_key(TestKeys::program_hash()));
+    });
+}
+
+#[test]
+fn test_failed_third_party_proof_is_rolled_back() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit(ALICE, 0, TestKeys::program_hash()));
+        let id = last_message_id();
+
+        // A failing proof from anyone but the sender changes nothing
+        assert_noop!(
+            Verifier::attach_proof(RuntimeOrigin::signed(BOB), id, test_proof(0, b"other key")),
+            Error::<Test>::VerificationFailed
+        );
+        assert_eq!(ProofAttempts::<Test>::get(id), 0);
+        assert_eq!(Messages::<Test>::get(id).unwrap().status, MessageStatus::Pending);
+
+        // A verifying one is kept
+        assert_ok!(Verifier::attach_proof(RuntimeOrigin::signed(BOB), id, test_proof(0, &TestKeys::key())));
+        assert_eq!(Messages::<Test>::get(id).unwrap().status, MessageStatus::Verified);
+    });
+}
+
+#[test]
+fn test_sender_proof_attempts_are_limited() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit(ALICE, 0, TestKeys::program_hash()));
+        let id = last_message_id();
+
+        // The sender's failing proofs are kept and counted
+        for attempt in 1..=3 {
+            assert_ok!(Verifier::attach_proof(RuntimeOrigin::signed(ALICE), id, test_proof(0, b"other key")));
+            assert_eq!(ProofAttempts::<Test>::get(id), attempt);
+            assert_eq!(Messages::<Test>::get(id).unwrap().status, MessageStatus::Failed);
+        }
+
+        assert_noop!(
+            Verifier::attach_proof(RuntimeOrigin::signed(ALICE), id, test_proof(0, &TestKeys::key())),
+            Error::<Test>::TooManyProofAttempts
+        );
+    });
+}
+
+#[test]
+fn test_sp1_backend_verifies_without_cached_program() {
+    new_test_ext().execute_with(|| {
+        Sp1Backend::set(true);
+        // The SP1 test key commits to the vkey hash [1; 32]
+        let program_hash = [1; 32];
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[program_hash])));
+        assert_ok!(Verifier::add_verification_key(RuntimeOrigin::root(), program_hash, groth16_key(2), None));
+
+        let public_inputs =
+            |nonce: u64| encode_public_inputs::<Blake2Scale>(ETHEREUM, POLKADOT, 0, nonce, [1; 32], program_hash, &[0, 1, 2]);
+
+        // A proof of other public inputs fails
+        assert_ok!(Verifier::submit_message(
+            RuntimeOrigin::signed(ALICE),
+            ETHEREUM,
+            POLKADOT,
+            0,
+            0,
+            [1; 32],
+            program_hash,
+            vec![0, 1, 2],
+            Some(groth16_proof(&public_inputs(1))),
+            None,
+        ));
+        let id = last_message_id();
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), id));
+        assert_eq!(Messages::<Test>::get(id).unwrap().status, MessageStatus::Failed);
+
+        assert_ok!(Verifier::attach_proof(RuntimeOrigin::signed(ALICE), id, groth16_proof(&public_inputs(0))));
+        assert_eq!(Messages::<Test>::get(id).unwrap().status, MessageStatus::Verified);
+        assert!(!ProgramCache::<Test>::contains_key(program_hash));
+    });
+}
+
+#[test]
+fn test_verify_message_requires_cached_program() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[TestKeys::program_hash()])));
+        assert_ok!(Verifier::add_verification_key(
+            RuntimeOrigin::root(),
+            TestKeys::program_hash(),
+            TestKeys::key(),
+            None
+        ));
+        assert_ok!(submit_proven(ALICE, 0, &TestKeys::key()));
+        let id = last_message_id();
+
+        assert_noop!(Verifier::verify_message(RuntimeOrigin::signed(BOB), id), Error::<Test>::ProgramNotFound);
+
+        // Caching the program makes the message verifiable
+        assert_ok!(Verifier::cache_program(RuntimeOrigin::root(), TestKeys::program_hash(), TestKeys::program()));
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), id));
+        assert_eq!(Messages::<Test>::get(id).unwrap().status, MessageStatus::Verified);
+    });
+}
+
+#[test]
+fn test_frozen_key_verifies_again_once_thawed() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit_proven(ALICE, 0, &TestKeys::key()));
+        let id = last_message_id();
+
+        assert_ok!(Verifier::freeze_verification_key(
+            RuntimeOrigin::root(),
+            TestKeys::program_hash(),
+            1,
+            b"test".to_vec()
+        ));
+        assert_noop!(Verifier::verify_message(RuntimeOrigin::signed(BOB), id), Error::<Test>::KeyNotActive);
+
+        assert_ok!(Verifier::thaw_verification_key(RuntimeOrigin::root(), TestKeys::program_hash(), 1));
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), id));
+        assert_eq!(Messages::<Test>::get(id).unwrap().status, MessageStatus::Verified);
+    });
+}
+
+#[test]
+fn test_revoked_key_no_longer_verifies() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit_proven(ALICE, 0, &TestKeys::key()));
+        let id = last_message_id();
+
+        assert_ok!(Verifier::revoke_verification_key(
+            RuntimeOrigin::root(),
+            TestKeys::program_hash(),
+            1,
+            b"test".to_vec()
+        ));
+        assert_noop!(Verifier::verify_message(RuntimeOrigin::signed(BOB), id), Error::<Test>::KeyNotActive);
+        // Revocation is permanent
+        assert_noop!(
+            Verifier::thaw_verification_key(RuntimeOrigin::root(), TestKeys::program_hash(), 1),
+            Error::<Test>::InvalidKeyStatusTransition
+        );
+
+        // Its pending messages can be failed by anyone
+        assert_ok!(Verifier::fail_messages_for_revoked_key(
+            RuntimeOrigin::signed(BOB),
+            TestKeys::program_hash(),
+            vec![id].try_into().unwrap()
+        ));
+        assert_eq!(Messages::<Test>::get(id).unwrap().status, MessageStatus::Failed);
+    });
+}
+
+#[test]
+fn test_unverified_messages_pin_their_program() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(Verifier::revoke_verification_key(
+            RuntimeOrigin::root(),
+            TestKeys::program_hash(),
+            1,
+            b"test".to_vec()
+        ));
+        assert!(!Verifier::is_program_pinned(&TestKeys::program_hash()));
+
+        assert_ok!(submit_proven(ALICE, 0, &TestKeys::key()));
+        let first = last_message_id();
+        assert_ok!(submit_proven(BOB, 1, &TestKeys::key()));
+        assert_eq!(ProgramMessageCount::<Test>::get(TestKeys::program_hash()), 2);
+        assert!(Verifier::is_program_pinned(&TestKeys::program_hash()));
+
+        assert_ok!(Verifier::cancel_message(RuntimeOrigin::signed(ALICE), first));
+        assert_eq!(ProgramMessageCount::<Test>::get(TestKeys::program_hash()), 1);
+
+        assert_ok!(Verifier::cancel_message(RuntimeOrigin::signed(BOB), last_message_id()));
+        assert!(!ProgramMessageCount::<Test>::contains_key(TestKeys::program_hash()));
+        assert!(!Verifier::is_program_pinned(&TestKeys::program_hash()));
+    });
+}
+
+/// Cache `len` bytes of `byte` as a program, returning its hash
+fn cache_test_program(byte: u8, len: usize) -> [u8; 32] {
+    let program = vec![byte; len];
+    let program_hash = sp_io::hashing::blake2_256(&program);
+    assert_ok!(Verifier::cache_program(RuntimeOrigin::root(), program_hash, program));
+    program_hash
+}
+
+#[test]
+fn test_cache_program_enforces_byte_budget() {
+    new_test_ext().execute_with(|| {
+        for byte in 1..=4 {
+            cache_test_program(byte, 1024);
+        }
+        assert_eq!(ProgramCacheBytes::<Test>::get(), 4096);
+
+        let program = vec![5; 1];
+        assert_noop!(
+            Verifier::cache_program(RuntimeOrigin::root(), sp_io::hashing::blake2_256(&program), program),
+            Error::<Test>::ProgramCacheFull
+        );
+        // Replacing a cached program does not count its old bytes
+        cache_test_program(1, 1024);
+        assert_eq!(ProgramCacheBytes::<Test>::get(), 4096);
+    });
+}
+
+#[test]
+fn test_eviction_keeps_new_and_pinned_programs() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        let pending = cache_test_program(1, 1024);
+        let unpinned = [cache_test_program(2, 1024), cache_test_program(3, 1024), cache_test_program(4, 700)];
+        // Above the eviction threshold of 90% of the budget
+        assert!(ProgramCacheBytes::<Test>::get() > 3686);
+
+        // Programs just cached are not evicted
+        Verifier::on_idle(1, Weight::MAX);
+        assert_eq!(ProgramCache::<Test>::iter().count(), 5);
+
+        System::set_block_number(60);
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[pending])));
+        assert_ok!(submit(ALICE, 0, pending));
+
+        // Stale programs go, unless an active key or a pending message pins them
+        System::set_block_number(101);
+        Verifier::on_idle(101, Weight::MAX);
+        for program_hash in unpinned {
+            assert!(!ProgramCache::<Test>::contains_key(program_hash));
+            assert!(!ProgramCacheUsage::<Test>::contains_key(program_hash));
+        }
+        assert!(ProgramCache::<Test>::contains_key(pending));
+        assert!(ProgramCache::<Test>::contains_key(TestKeys::program_hash()));
+        assert_eq!(ProgramCacheBytes::<Test>::get(), 1024 + TestKeys::program().len() as u64);
+    });
+}
+
+/// Begin an upload of `data` by `who` and append all its chunks
+fn upload(who: u64, target: UploadTarget, hash: [u8; 32], data: &[u8]) -> u64 {
+    let upload_id = NextUploadId::<Test>::get();
+    assert_ok!(Verifier::begin_upload(RuntimeOrigin::signed(who), target, hash, data.len() as u32));
+    for chunk in data.chunks(64) {
+        assert_ok!(Verifier::append_chunk(RuntimeOrigin::signed(who), upload_id, chunk.to_vec().try_into().unwrap()));
+    }
+    upload_id
+}
+
+fn add_test_key() {
+    assert_ok!(Verifier::add_verification_key(
+        RuntimeOrigin::root(),
+        TestKeys::program_hash(),
+        TestKeys::key(),
+        None
+    ));
+}
+
+#[test]
+fn test_upload_holds_base_and_byte_deposit() {
+    new_test_ext().execute_with(|| {
+        add_test_key();
+        let program = TestKeys::program();
+
+        let upload_id = upload(ALICE, UploadTarget::Program, TestKeys::program_hash(), &program);
+        let deposit = 20 + program.len() as u64;
+        assert_eq!(Uploads::<Test>::get(upload_id).unwrap().deposit, deposit);
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE - deposit);
+
+        assert_ok!(Verifier::cancel_upload(RuntimeOrigin::signed(ALICE), upload_id));
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE);
+
+        // Finalizing caches the program and releases the base deposit
+        let upload_id = upload(ALICE, UploadTarget::Program, TestKeys::program_hash(), &program);
+        assert_ok!(Verifier::finalize_upload(RuntimeOrigin::signed(ALICE), upload_id));
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE - program.len() as u64);
+        assert!(ProgramCache::<Test>::contains_key(TestKeys::program_hash()));
+        assert!(!Uploads::<Test>::contains_key(upload_id));
+    });
+}
+
+#[test]
+fn test_uploaded_program_deposit_is_held_until_eviction() {
+    new_test_ext().execute_with(|| {
+        add_test_key();
+        let program = TestKeys::program();
+        let program_hash = TestKeys::program_hash();
+        let upload_id = upload(ALICE, UploadTarget::Program, program_hash, &program);
+        assert_ok!(Verifier::finalize_upload(RuntimeOrigin::signed(ALICE), upload_id));
+        let deposit = program.len() as u64;
+        assert_eq!(ProgramDeposits::<Test>::get(program_hash), Some((ALICE, deposit)));
+        assert_eq!(Balances::balance_on_hold(&HoldReason::ProgramStorage.into(), &ALICE), deposit);
+
+        // A cached program cannot be uploaded again
+        assert_noop!(
+            Verifier::begin_upload(RuntimeOrigin::signed(BOB), UploadTarget::Program, program_hash, deposit as u32),
+            Error::<Test>::ProgramAlreadyCached
+        );
+
+        // The active key pins the program
+        System::set_block_number(101);
+        Verifier::on_idle(101, Weight::MAX);
+        assert!(ProgramCache::<Test>::contains_key(program_hash));
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE - deposit);
+
+        assert_ok!(Verifier::freeze_verification_key(RuntimeOrigin::root(), program_hash, 1, b"test".to_vec()));
+        Verifier::on_idle(101, Weight::MAX);
+        assert!(!ProgramCache::<Test>::contains_key(program_hash));
+        assert!(!ProgramDeposits::<Test>::contains_key(program_hash));
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE);
+        System::assert_has_event(
+            Event::ProgramDepositReleased { program_hash, who: ALICE, deposit_released: deposit }.into(),
+        );
+    });
+}
+
+#[test]
+fn test_upload_rejects_data_not_matching_hash() {
+    new_test_ext().execute_with(|| {
+        add_test_key();
+        assert_noop!(
+            Verifier::begin_upload(RuntimeOrigin::signed(ALICE), UploadTarget::Program, PROGRAM, 12),
+            Error::<Test>::NoKeyForProgram
+        );
+
+        let upload_id = upload(ALICE, UploadTarget::Program, TestKeys::program_hash(), b"other program");
+        assert_noop!(
+            Verifier::finalize_upload(RuntimeOrigin::signed(ALICE), upload_id),
+            Error::<Test>::UploadHashMismatch
+        );
+        assert!(!ProgramCache::<Test>::contains_key(TestKeys::program_hash()));
+    });
+}
+
+#[test]
+fn test_expired_uploads_are_rejected_and_removed() {
+    new_test_ext().execute_with(|| {
+        add_test_key();
+        let program = TestKeys::program();
+        let upload_id = NextUploadId::<Test>::get();
+        assert_ok!(Verifier::begin_upload(
+            RuntimeOrigin::signed(ALICE),
+            UploadTarget::Program,
+            TestKeys::program_hash(),
+            program.len() as u32
+        ));
+
+        System::set_block_number(22);
+        assert_noop!(
+            Verifier::append_chunk(RuntimeOrigin::signed(ALICE), upload_id, program.clone().try_into().unwrap()),
+            Error::<Test>::UploadExpired
+        );
+        assert_noop!(Verifier::finalize_upload(RuntimeOrigin::signed(ALICE), upload_id), Error::<Test>::UploadExpired);
+
+        Verifier::on_idle(22, Weight::MAX);
+        assert!(!Uploads::<Test>::contains_key(upload_id));
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE);
+    });
+}
+
+#[test]
+fn test_uploaded_proof_is_attached_and_verified() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit(ALICE, 0, TestKeys::program_hash()));
+        let id = last_message_id();
+
+        let proof = test_proof(0, &TestKeys::key());
+        let upload_id = upload(BOB, UploadTarget::Proof(id), sp_io::hashing::blake2_256(&proof), &proof);
+        assert_ok!(Verifier::finalize_upload(RuntimeOrigin::signed(BOB), upload_id));
+
+        let message = Messages::<Test>::get(id).unwrap();
+        assert_eq!(message.status, MessageStatus::Verified);
+        assert_eq!(message.proof.unwrap().into_inner(), proof);
+        assert_eq!(Balances::free_balance(BOB), INITIAL_BALANCE);
+    });
+}
+
+/// Submit a proven message for the test program escrowing `reward` for the relayer
+fn submit_with_reward(sender: u64, nonce: u64, reward: u64) -> sp_runtime::DispatchResult {
+    Verifier::submit_message(
+        RuntimeOrigin::signed(sender),
+        ETHEREUM,
+        POLKADOT,
+        0,
+        nonce,
+        [1; 32],
+        TestKeys::program_hash(),
+        vec![0, 1, 2],
+        Some(test_proof(nonce, &TestKeys::key())),
+        Some(reward),
+    )
+}
+
+#[test]
+fn test_relayer_is_paid_escrowed_reward() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit_with_reward(ALICE, 0, 30));
+        let id = last_message_id();
+        assert_eq!(MessageRewards::<Test>::get(id), Some(30));
+        assert_eq!(Balances::balance_on_hold(&HoldReason::RelayerReward.into(), &ALICE), 30);
+
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), id));
+        assert_eq!(Balances::free_balance(BOB), INITIAL_BALANCE + 30);
+        assert_eq!(Balances::balance_on_hold(&HoldReason::RelayerReward.into(), &ALICE), 0);
+        assert!(!MessageRewards::<Test>::contains_key(id));
+        System::assert_has_event(Event::RelayerRewarded { hash: id, relayer: BOB, amount: 30 }.into());
+    });
+}
+
+#[test]
+fn test_cancel_returns_escrowed_reward() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit_with_reward(ALICE, 0, 30));
+        let id = last_message_id();
+        // Message deposit and reward
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE - 10 - 30);
+
+        assert_ok!(Verifier::cancel_message(RuntimeOrigin::signed(ALICE), id));
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE);
+        assert!(!MessageRewards::<Test>::contains_key(id));
+    });
+}
+
+#[test]
+fn test_on_idle_prunes_expired_messages_across_blocks() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Verifier::set_route(RuntimeOrigin::root(), ETHEREUM, POLKADOT, route(&[PROGRAM])));
+        for nonce in 0..3 {
+            assert_ok!(submit(ALICE, nonce, PROGRAM));
+        }
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE - 30);
+
+        // Messages are kept until `MessageTtl` has passed
+        System::set_block_number(50);
+        Verifier::on_idle(50, Weight::MAX);
+        assert_eq!(Messages::<Test>::iter().count(), 3);
+
+        // A budget for two messages leaves the sweep to resume in the next block
+        let db = <Test as frame_system::Config>::DbWeight::get();
+        let budget = db.reads_writes(1, 1).saturating_add(db.reads_writes(4, 4).saturating_mul(2));
+        System::set_block_number(51);
+        Verifier::on_idle(51, budget);
+        assert_eq!(Messages::<Test>::iter().count(), 1);
+        assert!(PruneCursor::<Test>::get().is_some());
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE - 10);
+
+        System::set_block_number(52);
+        Verifier::on_idle(52, budget);
+        assert_eq!(Messages::<Test>::iter().count(), 0);
+        assert!(PruneCursor::<Test>::get().is_none());
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE);
+        assert!(!ProgramMessageCount::<Test>::contains_key(PROGRAM));
+    });
+}
+
+#[test]
+fn test_only_sender_cancels_message() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit(ALICE, 0, TestKeys::program_hash()));
+        let id = last_message_id();
+
+        assert_noop!(
+            Verifier::cancel_message(RuntimeOrigin::signed(BOB), id),
+            Error::<Test>::NotMessageSender
+        );
+        assert_noop!(Verifier::cancel_message(RuntimeOrigin::root(), id), DispatchError::BadOrigin);
+
+        assert_ok!(Verifier::cancel_message(RuntimeOrigin::signed(ALICE), id));
+        assert!(!Messages::<Test>::contains_key(id));
+        assert_eq!(Balances::balance_on_hold(&HoldReason::MessageDeposit.into(), &ALICE), 0);
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE);
+        System::assert_has_event(Event::MessageCancelled { hash: id, deposit_released: 10 }.into());
+    });
+}
+
+#[test]
+fn test_force_cancel_requires_force_origin() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit_with_reward(ALICE, 0, 30));
+        let id = last_message_id();
+
+        assert_noop!(
+            Verifier::force_cancel_message(RuntimeOrigin::signed(ALICE), id),
+            DispatchError::BadOrigin
+        );
+
+        // Governance cancels on the sender's behalf, returning deposit and reward to them
+        assert_ok!(Verifier::force_cancel_message(RuntimeOrigin::root(), id));
+        assert!(!Messages::<Test>::contains_key(id));
+        assert_eq!(Balances::free_balance(ALICE), INITIAL_BALANCE);
+    });
+}
+
+#[test]
+fn test_failed_messages_can_be_cancelled_but_verified_cannot() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit_proven(ALICE, 0, b"other key"));
+        let failed = last_message_id();
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), failed));
+        assert_eq!(Messages::<Test>::get(failed).unwrap().status, MessageStatus::Failed);
+        assert_ok!(Verifier::cancel_message(RuntimeOrigin::signed(ALICE), failed));
+        assert!(!ProofAttempts::<Test>::contains_key(failed));
+
+        assert_ok!(submit_proven(ALICE, 0, &TestKeys::key()));
+        let verified = last_message_id();
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), verified));
+        assert_noop!(
+            Verifier::cancel_message(RuntimeOrigin::signed(ALICE), verified),
+            Error::<Test>::InvalidStatusTransition
+        );
+        assert_noop!(
+            Verifier::force_cancel_message(RuntimeOrigin::root(), verified),
+            Error::<Test>::InvalidStatusTransition
+        );
+    });
+}
+
+/// Submit a message for the test program on `lane` with a proof under the test key
+fn submit_on_lane(sender: u64, lane: u32, nonce: u64) -> sp_runtime::DispatchResult {
+    let public_inputs =
+        encode_public_inputs::<Blake2Scale>(ETHEREUM, POLKADOT, lane, nonce, [1; 32], TestKeys::program_hash(), &[0, 1, 2]);
+    Verifier::submit_message(
+        RuntimeOrigin::signed(sender),
+        ETHEREUM,
+        POLKADOT,
+        lane,
+        nonce,
+        [1; 32],
+        TestKeys::program_hash(),
+        vec![0, 1, 2],
+        Some(TestProofs::prove(&TestKeys::key(), &public_inputs)),
+        None,
+    )
+}
+
+#[test]
+fn test_messages_are_verified_in_lane_order() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit_on_lane(ALICE, 0, 1));
+        let second = last_message_id();
+        assert_noop!(
+            Verifier::verify_message(RuntimeOrigin::signed(BOB), second),
+            Error::<Test>::MessageOutOfOrder
+        );
+
+        assert_ok!(submit_on_lane(ALICE, 0, 0));
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), last_message_id()));
+        assert_eq!(InboundLaneNonces::<Test>::get(ETHEREUM, 0), 1);
+
+        // The lane advanced, so the later message is now deliverable
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), second));
+        assert_eq!(InboundLaneNonces::<Test>::get(ETHEREUM, 0), 2);
+
+        // Delivered nonces cannot be submitted again
+        assert_noop!(submit_on_lane(BOB, 0, 1), Error::<Test>::NonceAlreadyUsed);
+    });
+}
+
+#[test]
+fn test_lanes_advance_independently() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit_on_lane(ALICE, 0, 1));
+        let blocked = last_message_id();
+
+        // A gap on lane 0 does not hold back lane 1
+        assert_ok!(submit_on_lane(ALICE, 1, 0));
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), last_message_id()));
+        assert_eq!(InboundLaneNonces::<Test>::get(ETHEREUM, 1), 1);
+        assert_eq!(InboundLaneNonces::<Test>::get(ETHEREUM, 0), 0);
+        assert_noop!(
+            Verifier::verify_message(RuntimeOrigin::signed(BOB), blocked),
+            Error::<Test>::MessageOutOfOrder
+        );
+
+        // The same nonce is accepted on another lane
+        assert_ok!(submit_on_lane(ALICE, 0, 0));
+    });
+}
+
+#[test]
+fn test_pause_requires_pause_origin() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Verifier::pause(RuntimeOrigin::signed(ALICE), PauseScope::Global, vec![]),
+            DispatchError::BadOrigin
+        );
+        assert_ok!(Verifier::pause(RuntimeOrigin::root(), PauseScope::Global, b"incident".to_vec()));
+        assert_eq!(Paused::<Test>::get(PauseScope::Global).unwrap().paused_by, None);
+
+        assert_noop!(Verifier::unpause(RuntimeOrigin::signed(ALICE), PauseScope::Global), DispatchError::BadOrigin);
+        assert_ok!(Verifier::unpause(RuntimeOrigin::root(), PauseScope::Global));
+        assert_noop!(Verifier::unpause(RuntimeOrigin::root(), PauseScope::Global), Error::<Test>::NotPaused);
+    });
+}
+
+#[test]
+fn test_pause_blocks_submission_and_verification_until_lifted() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        assert_ok!(submit_proven(ALICE, 0, &TestKeys::key()));
+        let id = last_message_id();
+
+        for scope in [PauseScope::Global, PauseScope::Chain(ETHEREUM), PauseScope::Route(ETHEREUM, POLKADOT)] {
+            assert_ok!(Verifier::pause(RuntimeOrigin::root(), scope, vec![]));
+            assert_noop!(submit_proven(ALICE, 1, &TestKeys::key()), Error::<Test>::Paused);
+            assert_noop!(Verifier::verify_message(RuntimeOrigin::signed(BOB), id), Error::<Test>::Paused);
+            assert_ok!(Verifier::unpause(RuntimeOrigin::root(), scope));
+        }
+
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), id));
+        assert_eq!(Messages::<Test>::get(id).unwrap().status, MessageStatus::Verified);
+        assert_ok!(submit_proven(ALICE, 1, &TestKeys::key()));
+    });
+}
+
+#[test]
+fn test_previous_key_verifies_only_during_grace_period() {
+    new_test_ext().execute_with(|| {
+        setup_test_program();
+        let new_key = [&TestKeys::program_hash()[..], b"new key"].concat();
+        assert_ok!(Verifier::add_verification_key(
+            RuntimeOrigin::root(),
+            TestKeys::program_hash(),
+            new_key.clone(),
+            None
+        ));
+        assert_ok!(submit_proven(ALICE, 0, &TestKeys::key()));
+        let during = last_message_id();
+        assert_ok!(submit_proven(ALICE, 1, &TestKeys::key()));
+        let after = last_message_id();
+
+        // The rotated out key is accepted for `KeyGracePeriod` blocks
+        System::set_block_number(21);
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), during));
+        let message = Messages::<Test>::get(during).unwrap();
+        assert_eq!(message.status, MessageStatus::Verified);
+        assert_eq!(message.key_version, Some(1));
+
+        System::set_block_number(22);
+        assert_ok!(Verifier::verify_message(RuntimeOrigin::signed(BOB), after));
+        assert_eq!(Messages::<Test>::get(after).unwrap().status, MessageStatus::Failed);
+
+        assert_ok!(Verifier::attach_proof(RuntimeOrigin::signed(ALICE), after, test_proof(1, &new_key)));
+        let message = Messages::<Test>::get(after).unwrap();
+        assert_eq!(message.status, MessageStatus::Verified);
+        assert_eq!(message.key_version, Some(2));
+    });
+}
+
+#[test]
+fn test_cache_program_requires_program_admin_origin() {
+    new_test_ext().execute_with(|| {
+        add_test_key();
+        for origin in [RuntimeOrigin::signed(ALICE), RuntimeOrigin::none()] {
+            assert_noop!(
+                Verifier::cache_program(origin, TestKeys::program_hash(), TestKeys::program()),
+                DispatchError::BadOrigin
+            );
+        }
+
+        assert_ok!(Verifier::cache_program(RuntimeOrigin::root(), TestKeys::program_hash(), TestKeys::program()));
+    });
+}
+
+#[test]
+fn test_key_management_requires_key_admin_origin() {
+    new_test_ext().execute_with(|| {
+        add_test_key();
+        let program_hash = TestKeys::program_hash();
+        let new_key = [&program_hash[..], b"new key"].concat();
+
+        for origin in [RuntimeOrigin::signed(ALICE), RuntimeOrigin::none()] {
+            assert_noop!(
+                Verifier::schedule_verification_key(origin.clone(), program_hash, new_key.clone(), None),
+                DispatchError::BadOrigin
+            );
+            assert_noop!(
+                Verifier::freeze_verification_key(origin.clone(), program_hash, 1, vec![]),
+                DispatchError::BadOrigin
+            );
+            assert_noop!(Verifier::thaw_verification_key(origin.clone(), program_hash, 1), DispatchError::BadOrigin);
+            assert_noop!(
+                Verifier::revoke_verification_key(origin, program_hash, 1, vec![]),
+                DispatchError::BadOrigin
+            );
+        }
+
+        assert_ok!(Verifier::schedule_verification_key(RuntimeOrigin::root(), program_hash, new_key, None));
+        assert_ok!(Verifier::freeze_verification_key(RuntimeOrigin::root(), program_hash, 1, vec![]));
+        assert_ok!(Verifier::thaw_verification_key(RuntimeOrigin::root(), program_hash, 1));
+        assert_ok!(Verifier::revoke_verification_key(RuntimeOrigin::root(), program_hash, 1, vec![]));
+    });
+}
+
+#[test]
+fn test_cancel_scheduled_key_requires_admin_or_depositor() {
+    new_test_ext().execute_with(|| {
+        let program_hash = TestKeys::program_hash();
+        assert_ok!(Verifier::schedule_verification_key(RuntimeOrigin::root(), program_hash, TestKeys::key(), None));
+
+        // Governance keys have no depositor, so signed accounts cannot cancel them
+        for origin in [RuntimeOrigin::signed(ALICE), RuntimeOrigin::none()] {
+            assert_noop!(Verifier::cancel_scheduled_key(origin, program_hash), DispatchError::BadOrigin);
+        }
+
+        assert_ok!(Verifier::cancel_scheduled_key(RuntimeOrigin::root(), program_hash));
+        assert!(!ScheduledKeys::<Test>::contains_key(program_hash));
+    });
+}
@@ -0,0 +1,127 @@
+//! Chunked uploads
+//!
+//! Programs and proofs larger than a practical extrinsic are uploaded in
+//! chunks. An upload is begun with the hash and length of the data, chunks
+//! are appended in order, and finalizing checks the hash before the data is
+//! cached as a program or attached as a proof. Every chunk except the last
+//! has the maximum chunk size, so an upload of `length` bytes has a known
+//! number of chunks.
+
+use sp_std::fmt::Debug;
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{CloneNoBound, EqNoBound, PartialEqNoBound, RuntimeDebugNoBound};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use crate::message_id::MessageId;
+
+/// Upload identifier, assigned sequentially
+pub type UploadId = u64;
+
+/// Where finalized upload data goes
+#[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum UploadTarget {
+    /// Program cached under the upload's hash
+    Program,
+    /// Proof attached to a message
+    Proof(MessageId),
+}
+
+/// An upload in progress
+#[derive(CloneNoBound, PartialEqNoBound, EqNoBound, Encode, Decode, RuntimeDebugNoBound, TypeInfo, MaxEncodedLen)]
+#[codec(mel_bound(AccountId: MaxEncodedLen, Balance: MaxEncodedLen))]
+pub struct Upload<AccountId: Clone + PartialEq + Eq + Debug, Balance: Clone + PartialEq + Eq + Debug> {
+    /// Account that began the upload and holds its deposit
+    pub owner: AccountId,
+    /// Where the data goes when finalized
+    pub target: UploadTarget,
+    /// Expected hash of the complete data
+    pub hash: [u8; 32],
+    /// Expected length of the complete data
+    pub length: u32,
+    /// Bytes received so far
+    pub received: u32,
+    /// Chunks received so far
+    pub chunks: u32,
+    /// Deposit held from `owner`
+    pub deposit: Balance,
+    /// Block after which the upload is removed if not finalized
+    pub expires_at: u64,
+}
+
+impl<AccountId: Clone + PartialEq + Eq + Debug, Balance: Clone + PartialEq + Eq + Debug> Upload<AccountId, Balance> {
+    /// Whether a chunk of `len` bytes can be appended
+    ///
+    /// Chunks must have `chunk_size` bytes, except the one completing the data.
+    pub fn accepts_chunk(&self, len: u32, chunk_size: u32) -> bool {
+        let remaining = self.length.saturating_sub(self.received);
+        len > 0 && (len == chunk_size.min(remaining))
+    }
+
+    /// Record an appended chunk of `len` bytes
+    pub fn record_chunk(&mut self, len: u32) {
+        self.received = self.received.saturating_add(len);
+        self.chunks = self.chunks.saturating_add(1);
+    }
+
+    /// Whether all data has been received
+    pub fn is_complete(&self) -> bool {
+        self.received == self.length
+    }
+
+    /// Whether the upload timed out at block `now`
+    pub fn is_expired(&self, now: u64) -> bool {
+        now > self.expires_at
+    }
+}
+
+/// Maximum number of chunks of an upload of `length` bytes
+pub fn max_chunks(length: u32, chunk_size: u32) -> u32 {
+    let chunk_size = chunk_size.max(1);
+    length / chunk_size + u32::from(length % chunk_size != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload(length: u32) -> Upload<u64, u64> {
+        Upload {
+            owner: 1,
+            target: UploadTarget::Program,
+            hash: [0; 32],
+            length,
+            received: 0,
+            chunks: 0,
+            deposit: 0,
+            expires_at: 10,
+        }
+    }
+
+    #[test]
+    fn test_chunks_are_full_except_the_last() {
+        let mut upload = upload(10);
+
+        assert!(!upload.accepts_chunk(3, 4));
+        assert!(upload.accepts_chunk(4, 4));
+        upload.record_chunk(4);
+        upload.record_chunk(4);
+
+        // Only the two remaining bytes complete the upload
+        assert!(!upload.accepts_chunk(4, 4));
+        assert!(!upload.accepts_chunk(1, 4));
+        assert!(upload.accepts_chunk(2, 4));
+        upload.record_chunk(2);
+
+        assert!(upload.is_complete());
+        assert_eq!(upload.chunks, max_chunks(10, 4));
+        assert!(!upload.accepts_chunk(0, 4));
+    }
+
+    #[test]
+    fn test_upload_expiry() {
+        let upload = upload(10);
+
+        assert!(!upload.is_expired(10));
+        assert!(upload.is_expired(11));
+    }
+}
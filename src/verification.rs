@@ -1,8 +1,8 @@
 use sp_std::prelude::*;
-use sp_runtime::traits::Hash;
+use sp_io::hashing::blake2_256;
 use codec::{Decode, Encode};
-use frostgate_circuits::sp1::{Sp1Backend, Sp1Config};
-use frostgate_zkip::{ZkBackend, ZkError};
+use frostgate_zkip::ZkError;
+use crate::message_id::{MessageHasher, MessageIdFields};
 
 /// Verification error types
 #[derive(Debug, Encode, Decode, PartialEq, Eq)]
@@ -22,9 +22,9 @@ pub enum VerificationError {
 impl From<ZkError> for VerificationError {
     fn from(error: ZkError) -> Self {
         match error {
-            ZkError::Program(msg) => VerificationError::InvalidProofFormat,
-            ZkError::VerificationFailed(msg) => VerificationError::VerificationFailed,
-            ZkError::Input(msg) => VerificationError::InvalidInput,
+            ZkError::Program(_) => VerificationError::InvalidProofFormat,
+            ZkError::VerificationFailed(_) => VerificationError::VerificationFailed,
+            ZkError::Input(_) => VerificationError::InvalidInput,
             _ => VerificationError::SystemError,
         }
     }
@@ -34,31 +34,127 @@ impl From<ZkError> for VerificationError {
 pub type VerificationResult = Result<(), VerificationError>;
 
 /// Proof verification context
+///
+/// Borrows the program, so that verifying against several keys does not copy it.
 #[derive(Clone)]
-pub struct VerificationContext {
-    /// Program bytes
-    pub program: Vec<u8>,
+pub struct VerificationContext<'a> {
+    /// Program bytes, empty for backends that do not need them
+    pub program: &'a [u8],
     /// Program hash
     pub program_hash: [u8; 32],
-    /// Backend instance
-    pub backend: Sp1Backend,
+    /// Prepared verification key, see [`KeyPreparer`]
+    pub verifying_key: Option<Vec<u8>>,
 }
 
-impl VerificationContext {
+impl<'a> VerificationContext<'a> {
     /// Create a new verification context
-    pub fn new(program: Vec<u8>, program_hash: [u8; 32]) -> Self {
-        let config = Sp1Config {
-            max_concurrent: Some(2), // Limited concurrency for on-chain verification
-            cache_size: 10,         // Small cache for on-chain use
-            use_gpu: false,         // No GPU for on-chain verification
-        };
-        
+    pub fn new(program: &'a [u8], program_hash: [u8; 32]) -> Self {
         Self {
             program,
             program_hash,
-            backend: Sp1Backend::with_config(config),
+            verifying_key: None,
         }
     }
+
+    /// Set the prepared verification key of the program
+    pub fn with_verifying_key(mut self, verifying_key: Vec<u8>) -> Self {
+        self.verifying_key = Some(verifying_key);
+        self
+    }
+}
+
+/// Derives program hashes from verification keys and program bytes
+///
+/// Keys and programs are registered under a claimed program hash. The pallet
+/// recomputes the hash with the runtime's `Config::ProgramHasher` and rejects
+/// the registration on a mismatch, so a mistyped hash fails immediately
+/// instead of leaving a key that never verifies.
+///
+/// Both methods must agree: a key and the program it verifies map to the
+/// same hash, since programs are cached and looked up under the hash their
+/// keys are registered with.
+pub trait ProgramHasher {
+    /// Program hash a verification key is for, `None` if the key cannot be decoded
+    ///
+    /// For SP1 this is the hash of the verifying key (`vk.bytes32()`), which
+    /// identifies the program.
+    fn key_program_hash(key_bytes: &[u8]) -> Option<[u8; 32]>;
+
+    /// Program hash of program bytes
+    ///
+    /// For SP1 this is the hash of the verifying key set up from the program.
+    fn program_hash(program_bytes: &[u8]) -> [u8; 32];
+}
+
+/// Blake2-256 program digests, with keys that lead with the digest of their program
+///
+/// A key is the 32-byte Blake2-256 digest of the program it verifies followed
+/// by the backend key, mirroring how SP1 keys commit to their program.
+/// Intended for development networks and tests; SP1 deployments derive the
+/// verifying key hash instead.
+pub struct Blake2Digest;
+
+impl ProgramHasher for Blake2Digest {
+    fn key_program_hash(key_bytes: &[u8]) -> Option<[u8; 32]> {
+        key_bytes.get(..32)?.try_into().ok()
+    }
+
+    fn program_hash(program_bytes: &[u8]) -> [u8; 32] {
+        blake2_256(program_bytes)
+    }
+}
+
+/// Parses verification keys into the form used during verification
+///
+/// Keys are prepared once at registration, so a malformed key is rejected
+/// up front and verification does not decode and check the key again. A
+/// backend implementation decodes the key canonically, checks that curve
+/// points are on the curve and in the prime-order subgroup and that the key
+/// expects the number of public inputs the pallet supplies, then returns its
+/// preprocessed encoding. See [`crate::sp1_keys`] for SP1 keys.
+pub trait KeyPreparer {
+    /// Check `key_bytes` and return the prepared key
+    fn prepare(key_bytes: &[u8]) -> Result<Vec<u8>, VerificationError>;
+}
+
+/// Uses key bytes unchanged, checking only that they are not empty
+///
+/// Intended for development networks and tests.
+pub struct RawKey;
+
+impl KeyPreparer for RawKey {
+    fn prepare(key_bytes: &[u8]) -> Result<Vec<u8>, VerificationError> {
+        if key_bytes.is_empty() {
+            return Err(VerificationError::InvalidInput);
+        }
+        Ok(key_bytes.to_vec())
+    }
+}
+
+/// Verifies proofs against prepared verification keys
+///
+/// Verification runs inside the runtime, so implementations are synchronous
+/// and deterministic. The key is the one prepared by the runtime's
+/// [`KeyPreparer`] when it was registered, so each key version verifies
+/// against its own key. See [`crate::sp1_keys`] for SP1 proofs.
+pub trait ProofVerifier {
+    /// Whether proofs are verified against the program bytes
+    ///
+    /// Backends whose keys identify the program, such as SP1 with its vkey
+    /// hash, return `false`: messages then verify without the program being
+    /// cached, and `verify` receives empty program bytes.
+    fn requires_program() -> bool {
+        true
+    }
+
+    /// Check that `proof` proves `public_inputs` for the program under `prepared_key`
+    fn verify(
+        program: &[u8],
+        program_hash: &[u8; 32],
+        prepared_key: &[u8],
+        proof: &[u8],
+        public_inputs: &[u8],
+    ) -> VerificationResult;
 }
 
 /// Proof verification parameters
@@ -71,43 +167,79 @@ pub struct VerificationParams<'a> {
     pub from_chain: u64,
     /// Destination chain ID
     pub to_chain: u64,
-    /// Message nonce
+    /// Source chain lane
+    pub lane: u32,
+    /// Source chain nonce
     pub nonce: u64,
     /// Message timestamp
     pub timestamp: u64,
 }
 
-/// Verify a proof using the configured backend
-pub async fn verify_proof(
-    context: &VerificationContext,
+/// Encode the public inputs a message proof must commit to
+///
+/// The inputs are the message ID preimage under the hasher `H`, see
+/// [`crate::message_id`]: the chains, lane, nonce, sender and program hash are
+/// committed directly and the payload through `H::payload_hash`, with the integer
+/// encoding of the hashing mode. An EVM source chain using
+/// [`crate::message_id::KeccakAbiPacked`] therefore proves the same bytes it
+/// hashes into the message ID.
+pub fn encode_public_inputs<H: MessageHasher>(
+    from_chain: u32,
+    to_chain: u32,
+    lane: u32,
+    nonce: u64,
+    source_sender: [u8; 32],
+    program_hash: [u8; 32],
+    payload: &[u8],
+) -> Vec<u8> {
+    H::public_inputs(&MessageIdFields::new(
+        from_chain,
+        to_chain,
+        lane,
+        nonce,
+        source_sender,
+        program_hash,
+        H::payload_hash(payload),
+    ))
+}
+
+/// Verify a proof against the prepared key of the context with the verifier `V`
+pub fn verify_proof<V: ProofVerifier>(
+    context: &VerificationContext<'_>,
     params: &VerificationParams<'_>,
 ) -> VerificationResult {
-    // Verify the proof
-    context.backend.verify(&context.program, params.proof, None)
-        .await
-        .map_err(|e| e.into())
-        .and_then(|valid| {
-            if valid {
-                Ok(())
-            } else {
-                Err(VerificationError::VerificationFailed)
-    }
-        })
+    let verifying_key = context.verifying_key.as_ref().ok_or(VerificationError::InvalidInput)?;
+    V::verify(context.program, &context.program_hash, verifying_key, params.proof, params.input)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use sp_core::H256;
+    use sp_io::hashing::keccak_256;
+    use crate::message_id::{Blake2Scale, KeccakAbiPacked};
 
-    #[tokio::test]
-    async fn test_proof_verification() {
+    /// Accepts proofs equal to the prepared key followed by the public inputs
+    struct EchoVerifier;
+
+    impl ProofVerifier for EchoVerifier {
+        fn verify(_: &[u8], _: &[u8; 32], prepared_key: &[u8], proof: &[u8], public_inputs: &[u8]) -> VerificationResult {
+            if proof == [prepared_key, public_inputs].concat() {
+                Ok(())
+            } else {
+                Err(VerificationError::VerificationFailed)
+            }
+        }
+    }
+
+    #[test]
+    fn test_proof_verification() {
         // Create a dummy program and hash
         let program = vec![1, 2, 3, 4];
         let program_hash = H256::from_slice(&[0; 32]).into();
 
         // Create verification context
-        let context = VerificationContext::new(program, program_hash);
+        let context = VerificationContext::new(&program, program_hash);
 
         // Create dummy proof and params
         let proof = vec![5, 6, 7, 8];
@@ -118,12 +250,71 @@ mod tests {
             input: &input,
             from_chain: 1,
             to_chain: 2,
+            lane: 0,
             nonce: 0,
             timestamp: 0,
         };
 
         // Test verification
-        let result = verify_proof(&context, &params).await;
+        let result = verify_proof::<EchoVerifier>(&context, &params);
+        assert!(result.is_err()); // Should fail without a key
+
+        // Each key verifies only proofs made for it
+        let context = context.with_verifying_key(vec![1, 2]);
+        let result = verify_proof::<EchoVerifier>(&context, &params);
         assert!(result.is_err()); // Should fail with dummy data
+
+        let proof = vec![1, 2, 9, 10, 11, 12];
+        assert_eq!(verify_proof::<EchoVerifier>(&context, &VerificationParams { proof: &proof, ..params }), Ok(()));
+        let context = VerificationContext::new(&program, [0; 32]).with_verifying_key(vec![1, 3]);
+        assert!(verify_proof::<EchoVerifier>(&context, &VerificationParams { proof: &proof, ..params }).is_err());
+    }
+
+    #[test]
+    fn test_blake2_digest_program_hashes() {
+        let program = vec![1, 2, 3];
+        let key = [&blake2_256(&program)[..], &[4, 5, 6]].concat();
+
+        assert_eq!(Blake2Digest::program_hash(&program), blake2_256(&program));
+        assert_ne!(Blake2Digest::program_hash(&program), Blake2Digest::program_hash(&[1, 2, 4]));
+
+        // A key and its program map to the same hash
+        assert_eq!(Blake2Digest::key_program_hash(&key), Some(Blake2Digest::program_hash(&program)));
+        assert_eq!(Blake2Digest::key_program_hash(&key[..31]), None);
+    }
+
+    #[test]
+    fn test_raw_key_preparation() {
+        assert_eq!(RawKey::prepare(&[1, 2, 3]), Ok(vec![1, 2, 3]));
+        assert_eq!(RawKey::prepare(&[]), Err(VerificationError::InvalidInput));
+    }
+
+    #[test]
+    fn test_public_inputs_bind_lane_nonce_sender_and_program() {
+        let payload = vec![1, 2, 3];
+        let encode = encode_public_inputs::<Blake2Scale>;
+        let inputs = encode(1, 2, 7, 42, [5; 32], [9; 32], &payload);
+
+        assert_ne!(inputs, encode(1, 2, 8, 42, [5; 32], [9; 32], &payload));
+        assert_ne!(inputs, encode(1, 2, 7, 43, [5; 32], [9; 32], &payload));
+        assert_ne!(inputs, encode(1, 2, 7, 42, [6; 32], [9; 32], &payload));
+        assert_ne!(inputs, encode(1, 2, 7, 42, [5; 32], [8; 32], &payload));
+        assert_ne!(inputs, encode(1, 2, 7, 42, [5; 32], [9; 32], &[1, 2, 4]));
+        assert_eq!(inputs, encode(1, 2, 7, 42, [5; 32], [9; 32], &payload));
+    }
+
+    #[test]
+    fn test_public_inputs_follow_the_hashing_mode() {
+        let payload = vec![1, 2, 3];
+        let fields = MessageIdFields::new(1, 2, 7, 42, [5; 32], [9; 32], keccak_256(&payload));
+
+        assert_eq!(
+            encode_public_inputs::<KeccakAbiPacked>(1, 2, 7, 42, [5; 32], [9; 32], &payload),
+            fields.abi_packed_preimage(),
+        );
+        assert_ne!(
+            encode_public_inputs::<KeccakAbiPacked>(1, 2, 7, 42, [5; 32], [9; 32], &payload),
+            encode_public_inputs::<Blake2Scale>(1, 2, 7, 42, [5; 32], [9; 32], &payload),
+        );
     }
 } 
\ No newline at end of file
@@ -0,0 +1,420 @@
+//! Weights for the Frostgate verifier pallet
+//!
+//! The figures below are estimates: the storage reads and writes are counted
+//! from each call's worst case and the execution times are conservative
+//! placeholders. Regenerate them on reference hardware from the benchmarks in
+//! `benchmarking.rs`, e.g.
+//!
+//! ```text
+//! node benchmark pallet --chain=dev --pallet=pallet_frostgate_verifier \
+//!     --extrinsic='*' --steps=50 --repeat=20 --output=src/weights.rs
+//! ```
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for the Frostgate verifier pallet
+pub trait WeightInfo {
+	fn submit_message() -> Weight;
+	fn verify_message() -> Weight;
+	fn attach_proof(p: u32, ) -> Weight;
+	fn set_route() -> Weight;
+	fn remove_route() -> Weight;
+	fn set_volume_limit() -> Weight;
+	fn register_verification_key() -> Weight;
+	fn commit_program_claim() -> Weight;
+	fn add_verification_key() -> Weight;
+	fn schedule_verification_key() -> Weight;
+	fn cancel_scheduled_key() -> Weight;
+	fn activate_scheduled_key() -> Weight;
+	fn remove_verification_key() -> Weight;
+	fn cache_program() -> Weight;
+	fn begin_upload() -> Weight;
+	fn append_chunk() -> Weight;
+	fn finalize_upload(c: u32, ) -> Weight;
+	fn cancel_upload(c: u32, ) -> Weight;
+	fn freeze_verification_key() -> Weight;
+	fn thaw_verification_key() -> Weight;
+	fn revoke_verification_key() -> Weight;
+	fn fail_messages_for_revoked_key(n: u32, ) -> Weight;
+	fn cancel_message() -> Weight;
+	fn force_cancel_message() -> Weight;
+	fn register_chain() -> Weight;
+	fn update_chain() -> Weight;
+	fn deregister_chain() -> Weight;
+	fn pause() -> Weight;
+	fn unpause() -> Weight;
+}
+
+/// Weights for the Frostgate verifier pallet using the runtime's database weights
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: Paused (r:4), Chains (r:2), Routes (r:1), InboundLaneNonces (r:1),
+	/// BlockMessageCount (r:1 w:1), SenderRateCounters (r:1 w:1), Messages (r:1 w:1),
+	/// MessageRewards (w:1), ProgramMessageCount (r:1 w:1), Balances Holds (r:2 w:2),
+	/// System Account (r:2 w:2)
+	fn submit_message() -> Weight {
+		Weight::from_parts(55_000_000, 8_000)
+			.saturating_add(T::DbWeight::get().reads(17_u64))
+			.saturating_add(T::DbWeight::get().writes(10_u64))
+	}
+	/// Storage: Messages (r:1 w:1), Paused (r:4), Chains (r:1), Routes (r:1),
+	/// InboundLaneNonces (r:1 w:1), ActiveKeyVersions (r:1), VerificationKeys (r:2),
+	/// KeyDeposits (r:2), ProgramCache (r:1), ProgramCacheUsage (r:1 w:1), ChainVolumeLimits (r:1),
+	/// ChainVolumeCounters (r:1 w:1), ProgramMessageCount (r:1 w:1), MessageRewards (r:1 w:1),
+	/// Balances Holds (r:1 w:1), System Account (r:2 w:2)
+	fn verify_message() -> Weight {
+		Weight::from_parts(405_000_000, 1_112_000)
+			.saturating_add(T::DbWeight::get().reads(22_u64))
+			.saturating_add(T::DbWeight::get().writes(9_u64))
+	}
+	/// Storage: as `verify_message`, plus ProofAttempts (r:1 w:1) and Messages (w:1)
+	/// The range of component `p` is `[1, MaxProofSize]`.
+	fn attach_proof(p: u32, ) -> Weight {
+		Weight::from_parts(420_000_000, 1_112_000)
+			// Proof decoding, storage and hashing into the message
+			.saturating_add(Weight::from_parts(3_000, 0).saturating_mul(p.into()))
+			.saturating_add(T::DbWeight::get().reads(23_u64))
+			.saturating_add(T::DbWeight::get().writes(11_u64))
+	}
+	/// Storage: Chains (r:2), Routes (r:1 w:1), ChainRouteCount (r:2 w:2)
+	fn set_route() -> Weight {
+		Weight::from_parts(18_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+	/// Storage: Routes (r:1 w:1), Paused (w:1), ChainRouteCount (r:2 w:2)
+	fn remove_route() -> Weight {
+		Weight::from_parts(16_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(4_u64))
+	}
+	/// Storage: Chains (r:1), ChainVolumeLimits (w:1)
+	fn set_volume_limit() -> Weight {
+		Weight::from_parts(12_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: ProgramOwners (r:1 w:1), ActiveKeyVersions (r:1), ScheduledKeys (r:1 w:1),
+	/// ProgramClaims (r:1 w:1), KeyDeposits (r:2), ScheduledKeyDeposits (w:1), KeyRegistrationNonces (r:1 w:1),
+	/// Balances Holds (r:1 w:1), System Account (r:1 w:1)
+	fn register_verification_key() -> Weight {
+		Weight::from_parts(90_000_000, 12_000)
+			.saturating_add(T::DbWeight::get().reads(9_u64))
+			.saturating_add(T::DbWeight::get().writes(7_u64))
+	}
+	/// Storage: ProgramClaims (r:1 w:1), Balances Holds (r:1 w:1), System Account (r:1 w:1)
+	fn commit_program_claim() -> Weight {
+		Weight::from_parts(40_000_000, 4_000)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+	/// Storage: ActiveKeyVersions (r:1 w:1), VerificationKeys (w:1)
+	fn add_verification_key() -> Weight {
+		Weight::from_parts(60_000_000, 10_000)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: ScheduledKeys (r:1 w:1)
+	fn schedule_verification_key() -> Weight {
+		Weight::from_parts(55_000_000, 10_000)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: ScheduledKeyDeposits (r:1 w:1), ScheduledKeys (r:1 w:1),
+	/// Balances Holds (r:1 w:1), System Account (r:1 w:1)
+	fn cancel_scheduled_key() -> Weight {
+		Weight::from_parts(40_000_000, 10_000)
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+			.saturating_add(T::DbWeight::get().writes(4_u64))
+	}
+	/// Storage: ScheduledKeys (r:1 w:1), ActiveKeyVersions (r:2 w:1),
+	/// ScheduledKeyDeposits (r:1 w:1), KeyDeposits (r:2 w:1), VerificationKeys (w:1)
+	fn activate_scheduled_key() -> Weight {
+		Weight::from_parts(35_000_000, 10_000)
+			.saturating_add(T::DbWeight::get().reads(6_u64))
+			.saturating_add(T::DbWeight::get().writes(5_u64))
+	}
+	/// Storage: KeyDeposits (r:1 w:1), VerificationKeys (r:1 w:1), ActiveKeyVersions (r:1),
+	/// Balances Holds (r:1 w:1), System Account (r:1 w:1)
+	fn remove_verification_key() -> Weight {
+		Weight::from_parts(35_000_000, 10_000)
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().writes(4_u64))
+	}
+	/// Storage: ProgramCache (w:1), ProgramCacheUsage (r:1 w:1), ProgramCacheBytes (r:1 w:1)
+	fn cache_program() -> Weight {
+		Weight::from_parts(45_000_000, 1_100_000)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+	/// Storage: ActiveKeyVersions (r:1), ScheduledKeys (r:1), ProgramCacheUsage (r:1),
+	/// NextUploadId (r:1 w:1), Uploads (w:1), Balances Holds (r:1 w:1), System Account (r:1 w:1)
+	fn begin_upload() -> Weight {
+		Weight::from_parts(40_000_000, 4_000)
+			.saturating_add(T::DbWeight::get().reads(6_u64))
+			.saturating_add(T::DbWeight::get().writes(4_u64))
+	}
+	/// Storage: Uploads (r:1 w:1), UploadChunks (w:1)
+	fn append_chunk() -> Weight {
+		Weight::from_parts(25_000_000, 70_000)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: Uploads (r:1 w:1), UploadChunks (r:c w:c), ProgramCache (w:1),
+	/// ProgramCacheUsage (r:1 w:1), ProgramCacheBytes (r:1 w:1), ProgramDeposits (w:1),
+	/// Balances Holds (r:1 w:1), System Account (r:1 w:1)
+	/// The range of component `c` is `[1, 1024]`.
+	fn finalize_upload(c: u32, ) -> Weight {
+		Weight::from_parts(60_000_000, 4_000)
+			// Chunk read, hashing and copy
+			.saturating_add(Weight::from_parts(1_500_000, 66_000).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(c.into())))
+			.saturating_add(T::DbWeight::get().writes(7_u64))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(c.into())))
+	}
+	/// Storage: Uploads (r:1 w:1), UploadChunks (w:c), Balances Holds (r:1 w:1),
+	/// System Account (r:1 w:1)
+	/// The range of component `c` is `[1, 1024]`.
+	fn cancel_upload(c: u32, ) -> Weight {
+		Weight::from_parts(35_000_000, 4_000)
+			.saturating_add(Weight::from_parts(500_000, 0).saturating_mul(c.into()))
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(c.into())))
+	}
+	/// Storage: VerificationKeys (r:1 w:1)
+	fn freeze_verification_key() -> Weight {
+		Weight::from_parts(22_000_000, 10_000)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: VerificationKeys (r:1 w:1)
+	fn thaw_verification_key() -> Weight {
+		Weight::from_parts(20_000_000, 10_000)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: VerificationKeys (r:1 w:1)
+	fn revoke_verification_key() -> Weight {
+		Weight::from_parts(22_000_000, 10_000)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: ActiveKeyVersions (r:1), VerificationKeys (r:2), Messages (r:n w:n)
+	/// The range of component `n` is `[1, MaxRevokedMessageBatch]`.
+	fn fail_messages_for_revoked_key(n: u32, ) -> Weight {
+		Weight::from_parts(25_000_000, 20_000)
+			.saturating_add(Weight::from_parts(12_000_000, 12_000).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(n.into())))
+	}
+	/// Storage: Messages (r:1 w:1), ProofAttempts (w:1), ProgramMessageCount (r:1 w:1),
+	/// MessageRewards (r:1 w:1), Balances Holds (r:1 w:1), System Account (r:1 w:1)
+	fn cancel_message() -> Weight {
+		Weight::from_parts(45_000_000, 12_000)
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().writes(6_u64))
+	}
+	/// Storage: Messages (r:1 w:1), ProofAttempts (w:1), ProgramMessageCount (r:1 w:1),
+	/// MessageRewards (r:1 w:1), Balances Holds (r:1 w:1), System Account (r:1 w:1)
+	fn force_cancel_message() -> Weight {
+		Weight::from_parts(45_000_000, 12_000)
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().writes(6_u64))
+	}
+	/// Storage: Chains (r:1 w:1)
+	fn register_chain() -> Weight {
+		Weight::from_parts(16_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: Chains (r:1 w:1)
+	fn update_chain() -> Weight {
+		Weight::from_parts(16_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: Chains (r:1 w:1), ChainRouteCount (r:1), Paused (r:1), ChainVolumeLimits (r:1),
+	/// InboundLaneNonces (r:1), ChainVolumeCounters (w:1)
+	fn deregister_chain() -> Weight {
+		Weight::from_parts(25_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: Routes (r:1), Paused (w:1)
+	fn pause() -> Weight {
+		Weight::from_parts(18_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: Paused (r:1 w:1)
+	fn unpause() -> Weight {
+		Weight::from_parts(16_000_000, 3_500)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn submit_message() -> Weight {
+		Weight::from_parts(55_000_000, 8_000)
+			.saturating_add(RocksDbWeight::get().reads(17_u64))
+			.saturating_add(RocksDbWeight::get().writes(10_u64))
+	}
+	fn verify_message() -> Weight {
+		Weight::from_parts(405_000_000, 1_112_000)
+			.saturating_add(RocksDbWeight::get().reads(22_u64))
+			.saturating_add(RocksDbWeight::get().writes(9_u64))
+	}
+	fn attach_proof(p: u32, ) -> Weight {
+		Weight::from_parts(420_000_000, 1_112_000)
+			.saturating_add(Weight::from_parts(3_000, 0).saturating_mul(p.into()))
+			.saturating_add(RocksDbWeight::get().reads(23_u64))
+			.saturating_add(RocksDbWeight::get().writes(11_u64))
+	}
+	fn set_route() -> Weight {
+		Weight::from_parts(18_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	fn remove_route() -> Weight {
+		Weight::from_parts(16_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+	}
+	fn set_volume_limit() -> Weight {
+		Weight::from_parts(12_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn register_verification_key() -> Weight {
+		Weight::from_parts(90_000_000, 12_000)
+			.saturating_add(RocksDbWeight::get().reads(9_u64))
+			.saturating_add(RocksDbWeight::get().writes(7_u64))
+	}
+	fn commit_program_claim() -> Weight {
+		Weight::from_parts(40_000_000, 4_000)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	fn add_verification_key() -> Weight {
+		Weight::from_parts(60_000_000, 10_000)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	fn schedule_verification_key() -> Weight {
+		Weight::from_parts(55_000_000, 10_000)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn cancel_scheduled_key() -> Weight {
+		Weight::from_parts(40_000_000, 10_000)
+			.saturating_add(RocksDbWeight::get().reads(4_u64))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+	}
+	fn activate_scheduled_key() -> Weight {
+		Weight::from_parts(35_000_000, 10_000)
+			.saturating_add(RocksDbWeight::get().reads(6_u64))
+			.saturating_add(RocksDbWeight::get().writes(5_u64))
+	}
+	fn remove_verification_key() -> Weight {
+		Weight::from_parts(35_000_000, 10_000)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+	}
+	fn cache_program() -> Weight {
+		Weight::from_parts(45_000_000, 1_100_000)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	fn begin_upload() -> Weight {
+		Weight::from_parts(40_000_000, 4_000)
+			.saturating_add(RocksDbWeight::get().reads(6_u64))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+	}
+	fn append_chunk() -> Weight {
+		Weight::from_parts(25_000_000, 70_000)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	fn finalize_upload(c: u32, ) -> Weight {
+		Weight::from_parts(60_000_000, 4_000)
+			.saturating_add(Weight::from_parts(1_500_000, 66_000).saturating_mul(c.into()))
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(c.into())))
+			.saturating_add(RocksDbWeight::get().writes(7_u64))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(c.into())))
+	}
+	fn cancel_upload(c: u32, ) -> Weight {
+		Weight::from_parts(35_000_000, 4_000)
+			.saturating_add(Weight::from_parts(500_000, 0).saturating_mul(c.into()))
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(c.into())))
+	}
+	fn freeze_verification_key() -> Weight {
+		Weight::from_parts(22_000_000, 10_000)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn thaw_verification_key() -> Weight {
+		Weight::from_parts(20_000_000, 10_000)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn revoke_verification_key() -> Weight {
+		Weight::from_parts(22_000_000, 10_000)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn fail_messages_for_revoked_key(n: u32, ) -> Weight {
+		Weight::from_parts(25_000_000, 20_000)
+			.saturating_add(Weight::from_parts(12_000_000, 12_000).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(n.into())))
+	}
+	fn cancel_message() -> Weight {
+		Weight::from_parts(45_000_000, 12_000)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(6_u64))
+	}
+	fn force_cancel_message() -> Weight {
+		Weight::from_parts(45_000_000, 12_000)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(6_u64))
+	}
+	fn register_chain() -> Weight {
+		Weight::from_parts(16_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn update_chain() -> Weight {
+		Weight::from_parts(16_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn deregister_chain() -> Weight {
+		Weight::from_parts(25_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	fn pause() -> Weight {
+		Weight::from_parts(18_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn unpause() -> Weight {
+		Weight::from_parts(16_000_000, 3_500)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+}